@@ -1,4 +1,11 @@
-use std::{cmp, iter};
+use std::{
+    cmp,
+    collections::{HashMap, HashSet},
+    env, fs,
+    io::{self, Write},
+    iter,
+    path::Path,
+};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 struct Point {
@@ -20,6 +27,79 @@ impl Point {
     }
 }
 
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// Smallest lattice step along a line, normalized so that lines drawn in either
+// direction share the same value
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct Direction {
+    step_x: i64,
+    step_y: i64,
+}
+
+impl Direction {
+    fn between(start: (i64, i64), finish: (i64, i64)) -> Direction {
+        let (dx, dy) = (finish.0 - start.0, finish.1 - start.1);
+        let divisor = gcd(dx, dy);
+        if divisor == 0 {
+            return Direction {
+                step_x: 1,
+                step_y: 0,
+            };
+        }
+        let (step_x, step_y) = (dx / divisor, dy / divisor);
+        if step_x < 0 || (step_x == 0 && step_y < 0) {
+            Direction {
+                step_x: -step_x,
+                step_y: -step_y,
+            }
+        } else {
+            Direction { step_x, step_y }
+        }
+    }
+
+    // Coefficients (a, b) of the line a*x + b*y = key shared by every line with this direction
+    fn coefficients(&self) -> (i64, i64) {
+        (self.step_y, -self.step_x)
+    }
+
+    fn key(&self, (x, y): (i64, i64)) -> i64 {
+        let (a, b) = self.coefficients();
+        a * x + b * y
+    }
+
+    // Position of a point along a line with this direction
+    fn param(&self, (x, y): (i64, i64)) -> i64 {
+        if self.step_x == 0 {
+            y
+        } else {
+            x
+        }
+    }
+
+    fn param_step(&self) -> i64 {
+        if self.step_x == 0 {
+            self.step_y
+        } else {
+            self.step_x
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Rasterization {
+    // Only the integer points lying exactly on the line
+    Lattice,
+    // Every cell the line passes through, including both cells beside a corner crossing
+    Supercover,
+}
+
 #[derive(Copy, Clone, Debug)]
 struct Line {
     start: Point,
@@ -50,44 +130,397 @@ impl Line {
         self.start.y == self.finish.y
     }
 
-    fn get_points(&self) -> Vec<Point> {
-        let x_iter: Box<dyn Iterator<Item = usize>>;
-        let y_iter: Box<dyn Iterator<Item = usize>>;
-        if self.is_horizontal() {
-            x_iter = Box::new(self.start.x..=self.finish.x);
-            y_iter = Box::new(iter::repeat(self.start.y));
-        } else if self.is_vertical() {
-            let y_start = cmp::min(self.start.y, self.finish.y);
-            let y_end = cmp::max(self.start.y, self.finish.y);
+    fn endpoints(&self) -> ((i64, i64), (i64, i64)) {
+        (
+            (self.start.x as i64, self.start.y as i64),
+            (self.finish.x as i64, self.finish.y as i64),
+        )
+    }
 
-            x_iter = Box::new(iter::repeat(self.start.x));
-            y_iter = Box::new(y_start..=y_end);
-        } else {
-            x_iter = Box::new(self.start.x..=self.finish.x);
-            if self.start.y < self.finish.y {
-                y_iter = Box::new(self.start.y..=self.finish.y);
+    fn direction(&self) -> Direction {
+        let (start, finish) = self.endpoints();
+        Direction::between(start, finish)
+    }
+
+    // (key, low, high) describing the line as an interval along its direction
+    fn span(&self) -> (i64, i64, i64) {
+        let direction = self.direction();
+        let (start, finish) = self.endpoints();
+        let (a, b) = (direction.param(start), direction.param(finish));
+        (direction.key(start), cmp::min(a, b), cmp::max(a, b))
+    }
+
+    fn get_points(&self, mode: Rasterization) -> Vec<Point> {
+        let (start, finish) = self.endpoints();
+        let (dx, dy) = (finish.0 - start.0, finish.1 - start.1);
+
+        let points: Vec<(i64, i64)> = match mode {
+            Rasterization::Lattice => {
+                let steps = gcd(dx, dy);
+                if steps == 0 {
+                    vec![start]
+                } else {
+                    let (step_x, step_y) = (dx / steps, dy / steps);
+                    (0..=steps)
+                        .map(|i| (start.0 + i * step_x, start.1 + i * step_y))
+                        .collect()
+                }
+            }
+            Rasterization::Supercover => {
+                let (nx, ny) = (dx.abs(), dy.abs());
+                let (sign_x, sign_y) = (dx.signum(), dy.signum());
+                let (mut ix, mut iy) = (0, 0);
+                let mut current = start;
+                let mut points = vec![current];
+                while ix < nx || iy < ny {
+                    // Compare where the line next crosses a vertical and a horizontal cell edge
+                    let decision = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+                    if decision == 0 {
+                        points.push((current.0 + sign_x, current.1));
+                        points.push((current.0, current.1 + sign_y));
+                        current = (current.0 + sign_x, current.1 + sign_y);
+                        ix += 1;
+                        iy += 1;
+                    } else if decision < 0 {
+                        current.0 += sign_x;
+                        ix += 1;
+                    } else {
+                        current.1 += sign_y;
+                        iy += 1;
+                    }
+                    points.push(current);
+                }
+                points
+            }
+        };
+
+        points
+            .into_iter()
+            .map(|(x, y)| Point {
+                x: x as usize,
+                y: y as usize,
+            })
+            .collect::<Vec<Point>>()
+    }
+}
+
+type Intervals = Vec<(i64, i64)>;
+
+// Sweeps inclusive intervals, returning the merged cover and the merged parts covered at least twice
+fn sweep_intervals(intervals: &[(i64, i64)]) -> (Intervals, Intervals) {
+    let mut events: Vec<(i64, i32)> = intervals
+        .iter()
+        .flat_map(|&(low, high)| [(low, 1), (high + 1, -1)])
+        .collect();
+    events.sort_unstable();
+
+    let mut union: Intervals = Vec::new();
+    let mut overlaps: Intervals = Vec::new();
+    let mut depth = 0;
+    let mut union_start = 0;
+    let mut overlap_start = 0;
+
+    for (pos, delta) in events {
+        let previous = depth;
+        depth += delta;
+        if previous == 0 && depth > 0 {
+            union_start = pos;
+        } else if previous > 0 && depth == 0 {
+            union.push((union_start, pos - 1));
+        }
+        if previous < 2 && depth >= 2 {
+            overlap_start = pos;
+        } else if previous >= 2 && depth < 2 {
+            overlaps.push((overlap_start, pos - 1));
+        }
+    }
+
+    for merged in [&mut union, &mut overlaps] {
+        merged.dedup_by(|next, prev| {
+            if next.0 == prev.1 + 1 {
+                prev.1 = next.1;
+                true
             } else {
-                y_iter = Box::new((self.finish.y..=self.start.y).rev());
+                false
             }
+        });
+    }
+
+    (union, overlaps)
+}
+
+fn interval_contains(intervals: &[(i64, i64)], t: i64) -> bool {
+    let index = intervals.partition_point(|&(_, high)| high < t);
+    index < intervals.len() && intervals[index].0 <= t
+}
+
+struct Coverage {
+    union: HashMap<i64, Intervals>,
+    overlaps: HashMap<i64, Intervals>,
+}
+
+impl Coverage {
+    fn from_lines<'a>(lines: impl Iterator<Item = &'a Line>) -> Coverage {
+        let mut by_key: HashMap<i64, Intervals> = HashMap::new();
+        for line in lines {
+            let (key, low, high) = line.span();
+            by_key.entry(key).or_default().push((low, high));
         }
 
-        x_iter
-            .zip(y_iter)
-            .map(|(x, y)| Point { x, y })
-            .collect::<Vec<Point>>()
+        let mut union = HashMap::new();
+        let mut overlaps = HashMap::new();
+        for (key, intervals) in by_key {
+            let (merged, doubled) = sweep_intervals(&intervals);
+            union.insert(key, merged);
+            if !doubled.is_empty() {
+                overlaps.insert(key, doubled);
+            }
+        }
+        Coverage { union, overlaps }
+    }
+
+    fn overlap_count(&self, direction: Direction) -> usize {
+        let step = direction.param_step();
+        self.overlaps
+            .values()
+            .flatten()
+            .map(|&(low, high)| ((high - low) / step + 1) as usize)
+            .sum()
+    }
+
+    fn overlaps_contain(&self, direction: Direction, point: (i64, i64)) -> bool {
+        self.overlaps
+            .get(&direction.key(point))
+            .is_some_and(|intervals| interval_contains(intervals, direction.param(point)))
+    }
+}
+
+// Lattice point where two lines of different directions cross, if any
+fn crossing(first: (Direction, i64), second: (Direction, i64)) -> Option<(i64, i64)> {
+    let ((a1, b1), (a2, b2)) = (first.0.coefficients(), second.0.coefficients());
+    let det = a1 * b2 - a2 * b1;
+    let x_num = first.1 * b2 - second.1 * b1;
+    let y_num = a1 * second.1 - a2 * first.1;
+    if x_num % det == 0 && y_num % det == 0 {
+        Some((x_num / det, y_num / det))
+    } else {
+        None
     }
 }
 
+// Counts points covered by at least two lines without rasterizing them. Collinear
+// lines are merged per direction with a sweep, and lines of different directions
+// are intersected pairwise, so the cost depends on the number of lines, not their length.
+fn count_overlaps(lines: &[Line]) -> usize {
+    let mut by_direction: HashMap<Direction, Vec<&Line>> = HashMap::new();
+    for line in lines {
+        by_direction.entry(line.direction()).or_default().push(line);
+    }
+    let coverages: Vec<(Direction, Coverage)> = by_direction
+        .into_iter()
+        .map(|(direction, lines)| (direction, Coverage::from_lines(lines.into_iter())))
+        .collect();
+
+    let mut crossings: HashSet<(i64, i64)> = HashSet::new();
+    for (index, (first, first_coverage)) in coverages.iter().enumerate() {
+        for (second, second_coverage) in &coverages[index + 1..] {
+            for (&first_key, first_intervals) in &first_coverage.union {
+                for (&second_key, second_intervals) in &second_coverage.union {
+                    if let Some(point) = crossing((*first, first_key), (*second, second_key)) {
+                        if interval_contains(first_intervals, first.param(point))
+                            && interval_contains(second_intervals, second.param(point))
+                        {
+                            crossings.insert(point);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // A crossing that lies in m per-direction overlaps is already counted m times
+    let within_directions: i64 = coverages
+        .iter()
+        .map(|(direction, coverage)| coverage.overlap_count(*direction) as i64)
+        .sum();
+    let correction: i64 = crossings
+        .into_iter()
+        .map(|point| {
+            let counted = coverages
+                .iter()
+                .filter(|(direction, coverage)| coverage.overlaps_contain(*direction, point))
+                .count();
+            1 - counted as i64
+        })
+        .sum();
+
+    (within_directions + correction) as usize
+}
+
 fn count_intersections(arr: &[Vec<i32>]) -> usize {
     arr.iter()
         .map(|row| row.iter().filter(|&ele| *ele > 1).count())
         .sum::<usize>()
 }
 
+fn fill_grid(lines: &[Line], mode: Rasterization) -> Vec<Vec<i32>> {
+    let mut arr = vec![vec![0; 1000]; 1000];
+    for line in lines {
+        for Point { x, y } in line.get_points(mode) {
+            arr[x][y] += 1;
+        }
+    }
+    arr
+}
+
+// Scales overlap counts to 0..=255 gray levels, one row of bytes per y value
+fn grayscale_rows(arr: &[Vec<i32>]) -> Vec<Vec<u8>> {
+    let max = arr.iter().flatten().copied().max().unwrap_or(0).max(1);
+    let height = arr.first().map_or(0, |column| column.len());
+    (0..height)
+        .map(|y| {
+            arr.iter()
+                .map(|column| (column[y] * 255 / max) as u8)
+                .collect()
+        })
+        .collect()
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+// Grayscale PNG using uncompressed deflate blocks, which keeps the encoder dependency free
+fn encode_png(rows: &[Vec<u8>]) -> Vec<u8> {
+    let width = rows.first().map_or(0, |row| row.len());
+    let raw: Vec<u8> = rows
+        .iter()
+        .flat_map(|row| iter::once(0).chain(row.iter().copied()))
+        .collect();
+
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = raw.chunks(0xFFFF).collect();
+    for (index, block) in blocks.iter().enumerate() {
+        zlib.push((index + 1 == blocks.len()) as u8);
+        let len = block.len() as u16;
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(rows.len() as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+    let mut out = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+    png_chunk(&mut out, b"IHDR", &header);
+    png_chunk(&mut out, b"IDAT", &zlib);
+    png_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+// Writes the grid as PGM, PNG or CSV depending on the file extension
+fn write_heatmap(arr: &[Vec<i32>], path: &Path) -> io::Result<()> {
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    let mut file = io::BufWriter::new(fs::File::create(path)?);
+    match extension {
+        "pgm" => {
+            let rows = grayscale_rows(arr);
+            let width = rows.first().map_or(0, |row| row.len());
+            write!(file, "P5\n{} {}\n255\n", width, rows.len())?;
+            for row in rows {
+                file.write_all(&row)?;
+            }
+        }
+        "png" => file.write_all(&encode_png(&grayscale_rows(arr)))?,
+        "csv" => {
+            let height = arr.first().map_or(0, |column| column.len());
+            for y in 0..height {
+                let row: Vec<String> = arr.iter().map(|column| column[y].to_string()).collect();
+                writeln!(file, "{}", row.join(","))?;
+            }
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported heatmap format: {:?}", path),
+            ))
+        }
+    }
+    file.flush()
+}
+
+// Writes the combined heatmap to `path` and each layer next to it, e.g. vents-gridlines.png
+fn export_heatmaps(path: &Path, layers: &[(&str, &[Vec<i32>])]) -> io::Result<()> {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("heatmap");
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    for (name, arr) in layers {
+        let layer_path = if name.is_empty() {
+            path.to_path_buf()
+        } else {
+            path.with_file_name(format!("{}-{}.{}", stem, name, extension))
+        };
+        write_heatmap(arr, &layer_path)?;
+    }
+    Ok(())
+}
+
 fn main() {
-    let contents = include_str!("day5.txt");
+    // `--input <file>` reads other vent lines. `--analytic` reports both parts from
+    // `count_overlaps` without filling the 1000x1000 grid, so coordinates may be any size.
+    // `--supercover` rasterizes with the supercover rule and `--heatmap <path>` exports
+    // the grid layers.
+    let args: Vec<String> = env::args().collect();
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .map(|index| args.get(index + 1).expect("missing flag value").as_str())
+    };
+    let mode = if args.iter().any(|arg| arg == "--supercover") {
+        Rasterization::Supercover
+    } else {
+        Rasterization::Lattice
+    };
 
-    let mut arr = vec![vec![0; 1000]; 1000];
+    let input = flag("--input").map(|path| fs::read_to_string(path).expect("failed to read input"));
+    let contents = match &input {
+        Some(input) => input.trim_end(),
+        None => include_str!("day5.txt"),
+    };
 
     let lines: Vec<Line> = contents.split('\n').map(Line::from_str).collect();
 
@@ -95,23 +528,198 @@ fn main() {
         .iter()
         .partition(|&line| line.is_horizontal() || line.is_vertical());
 
-    for line in gridlines {
-        let points = line.get_points();
-        for Point { x, y } in points {
-            arr[x][y] += 1;
-        }
+    if args.iter().any(|arg| arg == "--analytic") {
+        assert!(
+            mode == Rasterization::Lattice,
+            "--analytic counts lattice points and cannot be combined with --supercover"
+        );
+        println!("Part 1: {:?}", count_overlaps(&gridlines));
+        println!("Part 2: {:?}", count_overlaps(&lines));
+        return;
     }
 
+    let grid_layer = fill_grid(&gridlines, mode);
+    let mut arr = grid_layer.clone();
+
     let intersections1 = count_intersections(&arr);
     println!("Part 1: {:?}", intersections1);
 
-    for line in diagonals {
-        let points = line.get_points();
-        for Point { x, y } in points {
-            arr[x][y] += 1;
+    let diagonal_layer = fill_grid(&diagonals, mode);
+    for (column, diagonal_column) in arr.iter_mut().zip(&diagonal_layer) {
+        for (count, diagonal_count) in column.iter_mut().zip(diagonal_column) {
+            *count += diagonal_count;
         }
     }
 
     let intersections2 = count_intersections(&arr);
     println!("Part 2: {:?}", intersections2);
+
+    if let Some(path) = flag("--heatmap") {
+        let layers: [(&str, &[Vec<i32>]); 3] = [
+            ("", &arr),
+            ("gridlines", &grid_layer),
+            ("diagonals", &diagonal_layer),
+        ];
+        export_heatmaps(Path::new(path), &layers).expect("failed to write heatmap");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Vec<Line> {
+        contents.split('\n').map(Line::from_str).collect()
+    }
+
+    fn rasterized(lines: &[Line]) -> usize {
+        count_intersections(&fill_grid(lines, Rasterization::Lattice))
+    }
+
+    #[test]
+    fn overlaps_match_raster_on_puzzle() {
+        let lines = parse(include_str!("day5.txt"));
+        let gridlines: Vec<Line> = lines
+            .iter()
+            .filter(|line| line.is_horizontal() || line.is_vertical())
+            .copied()
+            .collect();
+        assert_eq!(count_overlaps(&gridlines), rasterized(&gridlines));
+        assert_eq!(count_overlaps(&lines), rasterized(&lines));
+    }
+
+    #[test]
+    fn overlaps_match_raster_on_mixed_slopes() {
+        let lines = parse(
+            "0,9 -> 5,9\n8,0 -> 0,8\n9,4 -> 3,4\n2,2 -> 2,1\n7,0 -> 7,4\n6,4 -> 2,0\n\
+             0,9 -> 2,9\n3,4 -> 1,4\n0,0 -> 8,8\n5,5 -> 8,2\n0,0 -> 6,3\n2,1 -> 8,4",
+        );
+        assert_eq!(count_overlaps(&lines), rasterized(&lines));
+    }
+
+    #[test]
+    fn overlaps_of_long_lines() {
+        let lines = parse("0,5 -> 2000000,5\n1000000,0 -> 1000000,9\n1500000,5 -> 3000000,5");
+        assert_eq!(count_overlaps(&lines), 500002);
+    }
+
+    fn cells(line: &str, mode: Rasterization) -> Vec<(usize, usize)> {
+        Line::from_str(line)
+            .get_points(mode)
+            .into_iter()
+            .map(|Point { x, y }| (x, y))
+            .collect()
+    }
+
+    #[test]
+    fn lattice_steps_by_gcd() {
+        assert_eq!(
+            cells("0,0 -> 6,3", Rasterization::Lattice),
+            vec![(0, 0), (2, 1), (4, 2), (6, 3)]
+        );
+        assert_eq!(
+            cells("9,1 -> 0,7", Rasterization::Lattice),
+            vec![(0, 7), (3, 5), (6, 3), (9, 1)]
+        );
+        assert_eq!(
+            cells("1,1 -> 4,2", Rasterization::Lattice),
+            vec![(1, 1), (4, 2)]
+        );
+    }
+
+    #[test]
+    fn supercover_includes_both_cells_at_corners() {
+        assert_eq!(
+            cells("0,0 -> 2,2", Rasterization::Supercover),
+            vec![(0, 0), (1, 0), (0, 1), (1, 1), (2, 1), (1, 2), (2, 2)]
+        );
+        assert_eq!(
+            cells("2,0 -> 0,2", Rasterization::Supercover),
+            vec![(0, 2), (1, 2), (0, 1), (1, 1), (2, 1), (1, 0), (2, 0)]
+        );
+        assert_eq!(
+            cells("0,0 -> 2,1", Rasterization::Supercover),
+            vec![(0, 0), (1, 0), (1, 1), (2, 1)]
+        );
+    }
+
+    fn heatmap(extension: &str, arr: &[Vec<i32>]) -> Vec<u8> {
+        let path = env::temp_dir().join(format!(
+            "day05-heatmap-{}.{}",
+            std::process::id(),
+            extension
+        ));
+        write_heatmap(arr, &path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        bytes
+    }
+
+    // Columns are x, so this is 3 pixels wide and 2 tall
+    fn sample() -> Vec<Vec<i32>> {
+        vec![vec![0, 1], vec![2, 3], vec![4, 0]]
+    }
+
+    #[test]
+    fn heatmap_csv_and_pgm() {
+        let csv = heatmap("csv", &sample());
+        assert_eq!(String::from_utf8(csv).unwrap(), "0,2,4\n1,3,0\n");
+
+        let pgm = heatmap("pgm", &sample());
+        let header = b"P5\n3 2\n255\n";
+        assert_eq!(&pgm[..header.len()], header);
+        assert_eq!(&pgm[header.len()..], &[0, 127, 255, 63, 191, 0]);
+
+        let path = env::temp_dir().join("day05-heatmap.bmp");
+        assert!(write_heatmap(&sample(), &path).is_err());
+    }
+
+    // Walks the PNG chunks, checking their CRCs, and inflates the stored deflate blocks
+    fn decode_png(png: &[u8]) -> ((u32, u32), Vec<u8>) {
+        let be32 = |bytes: &[u8]| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let mut offset = 8;
+        let mut size = (0, 0);
+        let mut zlib = Vec::new();
+        while offset < png.len() {
+            let len = be32(&png[offset..]) as usize;
+            let chunk = &png[offset + 4..offset + 8 + len];
+            assert_eq!(crc32(chunk), be32(&png[offset + 8 + len..]));
+            match &chunk[..4] {
+                b"IHDR" => size = (be32(&chunk[4..]), be32(&chunk[8..])),
+                b"IDAT" => zlib.extend_from_slice(&chunk[4..]),
+                _ => (),
+            }
+            offset += 12 + len;
+        }
+
+        let mut raw = Vec::new();
+        let mut position = 2;
+        loop {
+            let last = zlib[position] & 1 == 1;
+            let len = u16::from_le_bytes([zlib[position + 1], zlib[position + 2]]);
+            let nlen = u16::from_le_bytes([zlib[position + 3], zlib[position + 4]]);
+            assert_eq!(len, !nlen);
+            raw.extend_from_slice(&zlib[position + 5..position + 5 + len as usize]);
+            position += 5 + len as usize;
+            if last {
+                break;
+            }
+        }
+        assert_eq!(be32(&zlib[position..]), adler32(&raw));
+        (size, raw)
+    }
+
+    #[test]
+    fn heatmap_png_inflates_to_filtered_rows() {
+        let (size, raw) = decode_png(&heatmap("png", &sample()));
+        assert_eq!(size, (3, 2));
+        assert_eq!(raw, vec![0, 0, 127, 255, 0, 63, 191, 0]);
+
+        // Large enough to need several stored blocks
+        let arr = vec![vec![1; 300]; 400];
+        let (size, raw) = decode_png(&encode_png(&grayscale_rows(&arr)));
+        assert_eq!(size, (400, 300));
+        assert_eq!(raw.len(), 300 * 401);
+    }
 }
@@ -1,3 +1,380 @@
+use std::{
+    env, fmt, fs,
+    io::{self, Write},
+    iter,
+    ops::{Add, Mul},
+};
+
+// Arbitrary precision unsigned integer stored as little-endian base 2^32 limbs
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    fn from_u64(value: u64) -> BigUint {
+        let mut number = BigUint {
+            limbs: vec![value as u32, (value >> 32) as u32],
+        };
+        number.normalize();
+        number
+    }
+
+    fn normalize(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+}
+
+impl Add for BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: BigUint) -> BigUint {
+        let len = self.limbs.len().max(other.limbs.len());
+        let mut limbs = Vec::with_capacity(len + 1);
+        let mut carry = 0u64;
+        for i in 0..len {
+            let sum = *self.limbs.get(i).unwrap_or(&0) as u64
+                + *other.limbs.get(i).unwrap_or(&0) as u64
+                + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        limbs.push(carry as u32);
+        let mut number = BigUint { limbs };
+        number.normalize();
+        number
+    }
+}
+
+impl Mul for BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: BigUint) -> BigUint {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let product = a as u64 * b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        let mut number = BigUint { limbs };
+        number.normalize();
+        number
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Repeatedly divide by 10^9 to peel off decimal chunks
+        let mut limbs = self.limbs.clone();
+        let mut chunks: Vec<u32> = Vec::new();
+        while !limbs.is_empty() {
+            let mut remainder = 0u64;
+            for limb in limbs.iter_mut().rev() {
+                let current = (remainder << 32) | *limb as u64;
+                *limb = (current / 1_000_000_000) as u32;
+                remainder = current % 1_000_000_000;
+            }
+            chunks.push(remainder as u32);
+            while limbs.last() == Some(&0) {
+                limbs.pop();
+            }
+        }
+
+        match chunks.split_last() {
+            None => write!(f, "0"),
+            Some((first, rest)) => {
+                write!(f, "{}", first)?;
+                for chunk in rest.iter().rev() {
+                    write!(f, "{:09}", chunk)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Modular {
+    value: u64,
+    modulus: u64,
+}
+
+impl Modular {
+    fn new(value: u64, modulus: u64) -> Modular {
+        Modular {
+            value: value % modulus,
+            modulus,
+        }
+    }
+}
+
+impl fmt::Display for Modular {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+impl Add for Modular {
+    type Output = Modular;
+
+    fn add(self, other: Modular) -> Modular {
+        let sum = (self.value as u128 + other.value as u128) % self.modulus as u128;
+        Modular::new(sum as u64, self.modulus)
+    }
+}
+
+impl Mul for Modular {
+    type Output = Modular;
+
+    fn mul(self, other: Modular) -> Modular {
+        let product = (self.value as u128 * other.value as u128) % self.modulus as u128;
+        Modular::new(product as u64, self.modulus)
+    }
+}
+
+type Matrix<T> = Vec<Vec<T>>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Species {
+    name: String,
+    reset_timer: usize,
+    newborn_timer: usize,
+    offspring: u64,
+}
+
+impl Species {
+    fn lanternfish() -> Species {
+        Species {
+            name: String::from("lanternfish"),
+            reset_timer: 6,
+            newborn_timer: 8,
+            offspring: 1,
+        }
+    }
+
+    fn timers(&self) -> usize {
+        self.reset_timer.max(self.newborn_timer) + 1
+    }
+
+    // One day: every timer counts down, timer 0 resets and spawns the newborns
+    fn transition_matrix<T: Clone>(&self, lift: &impl Fn(u64) -> T) -> Matrix<T> {
+        let size = self.timers();
+        let mut counts = vec![vec![0u64; size]; size];
+        for timer in 0..size - 1 {
+            counts[timer][timer + 1] = 1;
+        }
+        counts[self.reset_timer][0] += 1;
+        counts[self.newborn_timer][0] += self.offspring;
+        counts
+            .iter()
+            .map(|row| row.iter().map(|&count| lift(count)).collect())
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Population {
+    species: Species,
+    fish: Vec<usize>,
+}
+
+impl Population {
+    // Parses a spec line such as `lanternfish reset=6 newborn=8 offspring=1 fish=3,4,3,1,2`
+    fn from_str(input: &str) -> Population {
+        let mut parts = input.split_whitespace();
+        let mut species = Species::lanternfish();
+        species.name = parts.next().expect("missing species name").to_string();
+        let mut fish = Vec::new();
+
+        for part in parts {
+            let (key, value) = part.split_once('=').expect("expected key=value");
+            match key {
+                "reset" => species.reset_timer = value.parse().expect("invalid reset timer"),
+                "newborn" => species.newborn_timer = value.parse().expect("invalid newborn timer"),
+                "offspring" => species.offspring = value.parse().expect("invalid offspring"),
+                "fish" => {
+                    fish = value
+                        .split(',')
+                        .map(|ele| ele.parse::<usize>().expect("invalid timer"))
+                        .collect()
+                }
+                _ => panic!("unknown species attribute: {}", key),
+            }
+        }
+
+        assert!(
+            fish.iter().all(|&timer| timer < species.timers()),
+            "{} has a timer above its lifecycle",
+            species.name
+        );
+        Population { species, fish }
+    }
+
+    fn histogram(&self) -> Vec<u64> {
+        let mut histogram = vec![0u64; self.species.timers()];
+        for &timer in &self.fish {
+            histogram[timer] += 1;
+        }
+        histogram
+    }
+
+    // Total after `days` days via fast exponentiation of the transition matrix.
+    // `lift` maps small integers into the count type, e.g. BigUint or Modular.
+    fn after<T>(&self, days: u64, lift: impl Fn(u64) -> T) -> T
+    where
+        T: Clone + Add<Output = T> + Mul<Output = T>,
+    {
+        let histogram = self.histogram();
+        let power = mat_pow(&self.species.transition_matrix(&lift), days, &lift);
+        power
+            .iter()
+            .flat_map(|row| {
+                row.iter()
+                    .zip(histogram.iter())
+                    .map(|(entry, &count)| entry.clone() * lift(count))
+            })
+            .fold(lift(0), |acc, ele| acc + ele)
+    }
+
+    // Population by timer value for day 0, day 1, and so on
+    fn timeline<'a, T>(&self, lift: impl Fn(u64) -> T + 'a) -> impl Iterator<Item = Vec<T>> + 'a
+    where
+        T: Clone + Add<Output = T> + Mul<Output = T> + 'a,
+    {
+        let matrix = self.species.transition_matrix(&lift);
+        let initial: Vec<T> = self.histogram().into_iter().map(&lift).collect();
+        iter::successors(Some(initial), move |histogram| {
+            Some(mat_vec(&matrix, histogram, &lift))
+        })
+    }
+}
+
+// Writes `species,day,total,timer0,timer1,...` rows for days 0 through `days`
+fn write_timeline<T>(
+    out: &mut impl Write,
+    populations: &[Population],
+    days: u64,
+    lift: impl Fn(u64) -> T + Copy,
+) -> io::Result<()>
+where
+    T: Clone + Add<Output = T> + Mul<Output = T> + fmt::Display,
+{
+    let timers = populations
+        .iter()
+        .map(|population| population.species.timers())
+        .max()
+        .unwrap_or(0);
+    let header: Vec<String> = (0..timers).map(|timer| format!("timer{}", timer)).collect();
+    writeln!(out, "species,day,total,{}", header.join(","))?;
+
+    for population in populations {
+        for (day, histogram) in population
+            .timeline(lift)
+            .take(days as usize + 1)
+            .enumerate()
+        {
+            let total = histogram
+                .iter()
+                .cloned()
+                .fold(lift(0), |acc, count| acc + count);
+            let mut counts: Vec<String> = histogram.iter().map(T::to_string).collect();
+            counts.resize(timers, String::new());
+            writeln!(
+                out,
+                "{},{},{},{}",
+                population.species.name,
+                day,
+                total,
+                counts.join(",")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Exact counts grow by roughly 9% a day. BigUint multiplies schoolbook style and prints
+// in quadratic time, so this horizon takes a couple of seconds; beyond it, use a modulus.
+const MAX_EXACT_DAYS: u64 = 500_000;
+
+fn check_exact_days(days: u64) -> Result<(), String> {
+    if days > MAX_EXACT_DAYS {
+        Err(format!(
+            "exact counts are limited to {} days, pass --modulus for {} days",
+            MAX_EXACT_DAYS, days
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+// Blank lines and lines starting with '#' are ignored
+fn parse_spec(contents: &str) -> Vec<Population> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(Population::from_str)
+        .collect()
+}
+
+fn mat_mul<T>(a: &Matrix<T>, b: &Matrix<T>, lift: &impl Fn(u64) -> T) -> Matrix<T>
+where
+    T: Clone + Add<Output = T> + Mul<Output = T>,
+{
+    (0..a.len())
+        .map(|row| {
+            (0..b[0].len())
+                .map(|col| {
+                    (0..b.len()).fold(lift(0), |acc, k| {
+                        acc + a[row][k].clone() * b[k][col].clone()
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn mat_vec<T>(matrix: &Matrix<T>, vector: &[T], lift: &impl Fn(u64) -> T) -> Vec<T>
+where
+    T: Clone + Add<Output = T> + Mul<Output = T>,
+{
+    matrix
+        .iter()
+        .map(|row| {
+            row.iter().zip(vector).fold(lift(0), |acc, (entry, count)| {
+                acc + entry.clone() * count.clone()
+            })
+        })
+        .collect()
+}
+
+fn mat_pow<T>(matrix: &Matrix<T>, mut exponent: u64, lift: &impl Fn(u64) -> T) -> Matrix<T>
+where
+    T: Clone + Add<Output = T> + Mul<Output = T>,
+{
+    let size = matrix.len();
+    let mut result: Matrix<T> = (0..size)
+        .map(|row| (0..size).map(|col| lift((row == col) as u64)).collect())
+        .collect();
+    let mut base = matrix.clone();
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mat_mul(&result, &base, lift);
+        }
+        exponent >>= 1;
+        if exponent > 0 {
+            base = mat_mul(&base, &base, lift);
+        }
+    }
+    result
+}
+
 fn calculate_fish(timer: usize, days: usize, memo: &mut [Vec<usize>]) -> usize {
     if memo[timer][days] != 0 {
         memo[timer][days]
@@ -34,4 +411,166 @@ fn main() {
         .sum();
 
     println!("Part 2: {:?}", p2_total);
+
+    // e.g. `--days 1000000000000000000 --modulus 1000000007`; without a modulus the
+    // exact count is printed, for at most MAX_EXACT_DAYS days. `--spec <file>` swaps
+    // the puzzle input for one or more species parsed with `parse_spec`, and
+    // `--timeline <file>` writes every day's timer histogram up to `--days` as CSV.
+    let args: Vec<String> = env::args().collect();
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .map(|index| args.get(index + 1).expect("missing flag value").as_str())
+    };
+    let populations = match flag("--spec") {
+        Some(path) => parse_spec(&fs::read_to_string(path).expect("failed to read spec file")),
+        None => vec![Population {
+            species: Species::lanternfish(),
+            fish,
+        }],
+    };
+    if let Some(days) = flag("--days") {
+        let days = days.parse::<u64>().expect("expected a day count");
+        let modulus = flag("--modulus").map(|modulus| {
+            match modulus.parse::<u64>().expect("expected a modulus") {
+                0 | 1 => panic!("--modulus must be at least 2, got {}", modulus),
+                modulus => modulus,
+            }
+        });
+        if modulus.is_none() {
+            check_exact_days(days).unwrap_or_else(|err| panic!("{}", err));
+        }
+        for population in &populations {
+            let name = &population.species.name;
+            match modulus {
+                Some(modulus) => {
+                    let total = population.after(days, |value| Modular::new(value, modulus));
+                    println!("{} day {} (mod {}): {}", name, days, modulus, total.value);
+                }
+                None => println!(
+                    "{} day {}: {}",
+                    name,
+                    days,
+                    population.after(days, BigUint::from_u64)
+                ),
+            }
+        }
+
+        if let Some(path) = flag("--timeline") {
+            let mut out =
+                io::BufWriter::new(fs::File::create(path).expect("failed to create timeline file"));
+            match modulus {
+                Some(modulus) => write_timeline(&mut out, &populations, days, |value| {
+                    Modular::new(value, modulus)
+                }),
+                None => write_timeline(&mut out, &populations, days, BigUint::from_u64),
+            }
+            .and_then(|_| out.flush())
+            .expect("failed to write timeline");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lanternfish() -> Population {
+        let fish = include_str!("day6.txt")
+            .split(',')
+            .map(|ele| ele.parse::<usize>().unwrap())
+            .collect();
+        Population {
+            species: Species::lanternfish(),
+            fish,
+        }
+    }
+
+    fn memoized_total(fish: &[usize], days: usize) -> u64 {
+        let mut memo: Vec<Vec<usize>> = vec![vec![0; 257]; 9];
+        fish.iter()
+            .map(|&timer| calculate_fish(timer, days, &mut memo) as u64)
+            .sum()
+    }
+
+    #[test]
+    fn matrix_matches_memoized_count() {
+        let population = lanternfish();
+        for days in [0, 1, 18, 80, 256] {
+            assert_eq!(
+                population.after(days as u64, BigUint::from_u64),
+                BigUint::from_u64(memoized_total(&population.fish, days))
+            );
+        }
+    }
+
+    #[test]
+    fn modular_matches_exact_count() {
+        let population = lanternfish();
+        let modulus = 1_000_000_007;
+        let exact = memoized_total(&population.fish, 256);
+        let reduced = population.after(256, |value| Modular::new(value, modulus));
+        assert_eq!(reduced.value, exact % modulus);
+    }
+
+    #[test]
+    fn exact_days_are_bounded() {
+        assert_eq!(check_exact_days(MAX_EXACT_DAYS), Ok(()));
+        assert!(check_exact_days(MAX_EXACT_DAYS + 1).is_err());
+        assert!(check_exact_days(1_000_000_000_000_000_000).is_err());
+    }
+
+    const TWO_SPECIES: &str = "# example fish and a faster breeder
+lanternfish fish=3,4,3,1,2
+
+rabbit reset=1 newborn=2 offspring=2 fish=0";
+
+    #[test]
+    fn spec_with_two_species() {
+        let populations = parse_spec(TWO_SPECIES);
+        assert_eq!(populations.len(), 2);
+        let (fish, rabbits) = (&populations[0], &populations[1]);
+        assert_eq!(fish.species, Species::lanternfish());
+        assert_eq!(
+            rabbits.species,
+            Species {
+                name: String::from("rabbit"),
+                reset_timer: 1,
+                newborn_timer: 2,
+                offspring: 2,
+            }
+        );
+
+        let total = |population: &Population, days| population.after(days, BigUint::from_u64);
+        assert_eq!(total(fish, 18), BigUint::from_u64(26));
+        assert_eq!(total(fish, 80), BigUint::from_u64(5934));
+        assert_eq!(total(fish, 256), BigUint::from_u64(26984457539));
+        // Rabbit timers by day: [1,0,0] [0,1,2] [1,2,0] [2,1,2] [1,4,4] [4,5,2]
+        let rabbit_totals: Vec<BigUint> = (0..6).map(|days| total(rabbits, days)).collect();
+        let expected: Vec<BigUint> = [1, 3, 3, 5, 9, 11]
+            .iter()
+            .map(|&count| BigUint::from_u64(count))
+            .collect();
+        assert_eq!(rabbit_totals, expected);
+    }
+
+    #[test]
+    fn timeline_rows() {
+        let mut out = Vec::new();
+        write_timeline(&mut out, &parse_spec(TWO_SPECIES), 2, BigUint::from_u64).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            rows,
+            vec![
+                "species,day,total,timer0,timer1,timer2,timer3,timer4,timer5,timer6,timer7,timer8",
+                "lanternfish,0,5,0,1,1,2,1,0,0,0,0",
+                "lanternfish,1,5,1,1,2,1,0,0,0,0,0",
+                "lanternfish,2,6,1,2,1,0,0,0,1,0,1",
+                "rabbit,0,1,1,0,0,,,,,,",
+                "rabbit,1,3,0,1,2,,,,,,",
+                "rabbit,2,3,1,2,0,,,,,,",
+            ]
+        );
+    }
 }
@@ -1,33 +1,303 @@
-fn p2_fuel(crabs: &[i32], meeting: i32) -> i32 {
+use std::env;
+
+trait FuelCost {
+    // Fuel for a single crab to move `distance` steps. Must be convex and
+    // non-decreasing in the distance for `optimal_meeting` to be exact.
+    fn cost(&self, distance: i64) -> i64;
+}
+
+struct Linear;
+
+struct Triangular;
+
+struct Quadratic;
+
+impl FuelCost for Linear {
+    fn cost(&self, distance: i64) -> i64 {
+        distance
+    }
+}
+
+impl FuelCost for Triangular {
+    fn cost(&self, distance: i64) -> i64 {
+        distance * (distance + 1) / 2
+    }
+}
+
+impl FuelCost for Quadratic {
+    fn cost(&self, distance: i64) -> i64 {
+        distance * distance
+    }
+}
+
+impl<F: Fn(i64) -> i64> FuelCost for F {
+    fn cost(&self, distance: i64) -> i64 {
+        self(distance)
+    }
+}
+
+fn total_fuel(crabs: &[i64], meeting: i64, cost: &dyn FuelCost) -> i64 {
     crabs
         .iter()
-        .map(|pos| {
-            let diff = (*pos - meeting).abs();
-            diff * (diff + 1) / 2
-        })
+        .map(|pos| cost.cost((*pos - meeting).abs()))
         .sum()
 }
 
+// Total fuel is convex in the meeting point, so binary search for the first
+// position where moving one step further right stops helping. `crabs` must be sorted.
+fn optimal_meeting(crabs: &[i64], cost: &dyn FuelCost) -> (i64, i64) {
+    let (mut low, mut high) = (crabs[0], crabs[crabs.len() - 1]);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if total_fuel(crabs, mid + 1, cost) >= total_fuel(crabs, mid, cost) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    (low, total_fuel(crabs, low, cost))
+}
+
+struct RallyPlan {
+    // (meeting point, first crab, one past the last crab) into the sorted crabs
+    rallies: Vec<(i64, usize, usize)>,
+    fuel: i64,
+}
+
+// Optimal rally for crabs[start..end] with the linear cost, i.e. the segment median,
+// using prefix sums so each segment costs O(1)
+fn median_segments(crabs: &[i64]) -> impl Fn(usize, usize) -> (i64, i64) + '_ {
+    let mut prefix = vec![0i64; crabs.len() + 1];
+    for (index, pos) in crabs.iter().enumerate() {
+        prefix[index + 1] = prefix[index] + pos;
+    }
+    move |start, end| {
+        let mid = (start + end) / 2;
+        let median = crabs[mid];
+        let below = median * (mid - start) as i64 - (prefix[mid] - prefix[start]);
+        let above = (prefix[end] - prefix[mid]) - median * (end - mid) as i64;
+        (median, below + above)
+    }
+}
+
+// Splits the sorted crabs into at most k groups, each meeting at its own point, minimizing
+// the total fuel. With a convex cost the optimal groups are contiguous runs of the sorted
+// crabs, so a DP over (groups used, crabs covered) is exact. `segment(start, end)` gives
+// the best meeting point and fuel for crabs[start..end].
+fn plan_rallies(
+    crabs: &[i64],
+    k: usize,
+    segment: impl Fn(usize, usize) -> (i64, i64),
+) -> RallyPlan {
+    let n = crabs.len();
+    if n == 0 {
+        return RallyPlan {
+            rallies: Vec::new(),
+            fuel: 0,
+        };
+    }
+    let k = k.clamp(1, n);
+    let mut best = vec![vec![i64::MAX; n + 1]; k + 1];
+    let mut split = vec![vec![0usize; n + 1]; k + 1];
+    best[0][0] = 0;
+
+    for groups in 1..=k {
+        for end in groups..=n {
+            for start in groups - 1..end {
+                if best[groups - 1][start] == i64::MAX {
+                    continue;
+                }
+                let fuel = best[groups - 1][start] + segment(start, end).1;
+                if fuel < best[groups][end] {
+                    best[groups][end] = fuel;
+                    split[groups][end] = start;
+                }
+            }
+        }
+    }
+
+    let groups = (1..=k).min_by_key(|&groups| best[groups][n]).unwrap();
+    let mut rallies = Vec::with_capacity(groups);
+    let mut end = n;
+    for used in (1..=groups).rev() {
+        let start = split[used][end];
+        rallies.push((segment(start, end).0, start, end));
+        end = start;
+    }
+    rallies.reverse();
+
+    RallyPlan {
+        rallies,
+        fuel: best[groups][n],
+    }
+}
+
 fn main() {
     let contents = include_str!("day7.txt");
 
-    let mut crabs: Vec<i32> = contents
+    let mut crabs: Vec<i64> = contents
         .split(',')
-        .map(|ele| ele.parse::<i32>().unwrap())
+        .map(|ele| ele.parse::<i64>().unwrap())
         .collect();
 
     crabs.sort_unstable();
-    let num_crabs = crabs.len();
-    let median = crabs[num_crabs / 2];
 
-    let p1_fuel: i32 = crabs.iter().map(|pos| (*pos - median).abs()).sum();
+    let (_, p1_fuel) = optimal_meeting(&crabs, &Linear);
 
     println!("Part 1: {:?}", p1_fuel);
 
-    let p2_fuel: i32 = (0..1000)
-        .map(|meeting| p2_fuel(&crabs, meeting))
-        .min()
-        .unwrap();
+    let (_, p2_fuel) = optimal_meeting(&crabs, &Triangular);
 
     println!("Part 2: {:?}", p2_fuel);
+
+    // `--cost quadratic` or `--cost power=3` reports the optimum for other cost models.
+    // `--rallies k` splits the crabs over up to k meeting points, with the linear cost
+    // unless `--cost` is also given. The linear cost uses prefix-sum medians; any other
+    // cost binary searches every segment, about O(k * n^3 * log range), so on the full
+    // input `--cost triangular --rallies 2` already takes several seconds.
+    let args: Vec<String> = env::args().collect();
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .map(|index| args.get(index + 1).expect("missing flag value").as_str())
+    };
+    let cost = flag("--cost").map(|name| {
+        let cost: Box<dyn FuelCost> = match name.split_once('=') {
+            Some(("power", exponent)) => {
+                let exponent = exponent.parse::<u32>().expect("invalid exponent");
+                Box::new(move |distance: i64| distance.pow(exponent))
+            }
+            _ => match name {
+                "linear" => Box::new(Linear),
+                "triangular" => Box::new(Triangular),
+                "quadratic" => Box::new(Quadratic),
+                _ => panic!("unknown cost model: {}", name),
+            },
+        };
+        let (position, fuel) = optimal_meeting(&crabs, cost.as_ref());
+        println!("{}: position {}, fuel {}", name, position, fuel);
+        cost
+    });
+
+    if let Some(k) = flag("--rallies") {
+        let k = k.parse::<usize>().expect("invalid rally count");
+        let plan = match &cost {
+            Some(cost) => plan_rallies(&crabs, k, |start, end| {
+                optimal_meeting(&crabs[start..end], cost.as_ref())
+            }),
+            None => plan_rallies(&crabs, k, median_segments(&crabs)),
+        };
+        println!("Rallies: fuel {}", plan.fuel);
+        for (point, start, end) in plan.rallies {
+            println!(
+                "  position {}: {} crabs from {} to {}",
+                point,
+                end - start,
+                crabs[start],
+                crabs[end - 1]
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force(crabs: &[i64], cost: &dyn FuelCost) -> i64 {
+        (crabs[0]..=crabs[crabs.len() - 1])
+            .map(|meeting| total_fuel(crabs, meeting, cost))
+            .min()
+            .unwrap()
+    }
+
+    fn assert_optimal(crabs: &[i64], cost: &dyn FuelCost) {
+        let (position, fuel) = optimal_meeting(crabs, cost);
+        assert_eq!(fuel, total_fuel(crabs, position, cost));
+        assert_eq!(fuel, brute_force(crabs, cost), "{:?}", crabs);
+    }
+
+    #[test]
+    fn optimum_matches_scan() {
+        let example = [0, 1, 1, 2, 2, 2, 4, 7, 14, 16];
+        assert_eq!(optimal_meeting(&example, &Linear), (2, 37));
+        assert_eq!(optimal_meeting(&example, &Triangular), (5, 168));
+
+        let wide = [-1500, -20, 3, 3, 999, 1000, 1001, 2400, 5000];
+        let single = [4200];
+        for crabs in [&example[..], &wide[..], &single[..]] {
+            assert_optimal(crabs, &Linear);
+            assert_optimal(crabs, &Triangular);
+            assert_optimal(crabs, &Quadratic);
+            assert_optimal(crabs, &|distance: i64| distance.pow(3));
+            assert_optimal(crabs, &|distance: i64| 3 * distance + distance / 2);
+        }
+    }
+
+    // Tries every assignment of crabs to k groups, not just contiguous runs
+    fn brute_force_rallies(crabs: &[i64], k: usize, cost: &dyn FuelCost) -> i64 {
+        let assignments = k.pow(crabs.len() as u32);
+        (0..assignments)
+            .map(|mut assignment| {
+                let mut groups = vec![Vec::new(); k];
+                for &crab in crabs {
+                    groups[assignment % k].push(crab);
+                    assignment /= k;
+                }
+                groups
+                    .iter()
+                    .filter(|group| !group.is_empty())
+                    .map(|group| brute_force(group, cost))
+                    .sum()
+            })
+            .min()
+            .unwrap()
+    }
+
+    fn assert_plan(crabs: &[i64], k: usize, plan: &RallyPlan, cost: &dyn FuelCost) {
+        assert_eq!(
+            plan.fuel,
+            brute_force_rallies(crabs, k, cost),
+            "{:?}",
+            crabs
+        );
+        assert!(plan.rallies.len() <= k);
+        let mut covered = 0;
+        let mut fuel = 0;
+        for &(point, start, end) in &plan.rallies {
+            assert_eq!(start, covered);
+            fuel += total_fuel(&crabs[start..end], point, cost);
+            covered = end;
+        }
+        assert_eq!(covered, crabs.len());
+        assert_eq!(fuel, plan.fuel);
+    }
+
+    #[test]
+    fn rallies_match_brute_force() {
+        let inputs: [&[i64]; 4] = [
+            &[0, 1, 1, 2, 4, 7, 14],
+            &[-30, -29, 0, 5, 6, 40, 41],
+            &[3, 3, 3, 3],
+            &[1, 100],
+        ];
+        for crabs in inputs {
+            for k in 1..=3 {
+                let plan = plan_rallies(crabs, k, median_segments(crabs));
+                assert_plan(crabs, k, &plan, &Linear);
+
+                let plan = plan_rallies(crabs, k, |start, end| {
+                    optimal_meeting(&crabs[start..end], &Triangular)
+                });
+                assert_plan(crabs, k, &plan, &Triangular);
+            }
+        }
+    }
+
+    #[test]
+    fn rallies_without_crabs() {
+        let plan = plan_rallies(&[], 3, median_segments(&[]));
+        assert!(plan.rallies.is_empty());
+        assert_eq!(plan.fuel, 0);
+    }
 }
@@ -1,4 +1,7 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+};
 
 fn find_by_len<T>(input: &[HashSet<T>], len: usize) -> Option<&HashSet<T>> {
     input.iter().find(|&ele| ele.len() == len)
@@ -10,46 +13,41 @@ fn alphabetize(s: &str) -> String {
     temp.into_iter().collect::<String>()
 }
 
-fn construct_mappings(input: &[&str]) -> HashMap<String, usize> {
+// None when the patterns don't contain the ten standard digits
+fn construct_mappings(input: &[&str]) -> Option<HashMap<String, usize>> {
     let input_sets: Vec<HashSet<char>> = input
         .iter()
         .map(|&digit| digit.chars().collect::<HashSet<_>>())
         .collect();
 
-    let one = find_by_len(&input_sets, 2).unwrap();
-    let seven = find_by_len(&input_sets, 3).unwrap();
-    let four = find_by_len(&input_sets, 4).unwrap();
-    let eight = find_by_len(&input_sets, 7).unwrap();
+    let one = find_by_len(&input_sets, 2)?;
+    let seven = find_by_len(&input_sets, 3)?;
+    let four = find_by_len(&input_sets, 4)?;
+    let eight = find_by_len(&input_sets, 7)?;
 
     let nine = input_sets
         .iter()
-        .find(|&ele| ele.len() == 6 && ele.intersection(four).count() == 4)
-        .unwrap();
+        .find(|&ele| ele.len() == 6 && ele.intersection(four).count() == 4)?;
 
     let three = input_sets
         .iter()
-        .find(|&ele| ele.len() == 5 && ele.intersection(one).count() == 2)
-        .unwrap();
+        .find(|&ele| ele.len() == 5 && ele.intersection(one).count() == 2)?;
 
     let zero = input_sets
         .iter()
-        .find(|&ele| ele.len() == 6 && ele != nine && ele.intersection(one).count() == 2)
-        .unwrap();
+        .find(|&ele| ele.len() == 6 && ele != nine && ele.intersection(one).count() == 2)?;
 
     let six = input_sets
         .iter()
-        .find(|&ele| ele.len() == 6 && ele != zero && ele != nine)
-        .unwrap();
+        .find(|&ele| ele.len() == 6 && ele != zero && ele != nine)?;
 
     let five = input_sets
         .iter()
-        .find(|&ele| ele.len() == 5 && ele.is_subset(six))
-        .unwrap();
+        .find(|&ele| ele.len() == 5 && ele.is_subset(six))?;
 
     let two = input_sets
         .iter()
-        .find(|&ele| ele.len() == 5 && ele != three && ele != five)
-        .unwrap();
+        .find(|&ele| ele.len() == 5 && ele != three && ele != five)?;
 
     let values = vec![
         alphabetize(&zero.iter().collect::<String>()),
@@ -68,25 +66,431 @@ fn construct_mappings(input: &[&str]) -> HashMap<String, usize> {
     for (i, value) in values.iter().enumerate() {
         mappings.insert(value.clone(), i);
     }
-    mappings
+    Some(mappings)
+}
+
+// Wires are the letters a to z, so every pattern fits in a u32 mask
+fn check_pattern(pattern: &str) -> Result<(), String> {
+    match pattern.chars().find(|wire| !wire.is_ascii_lowercase()) {
+        Some(wire) => Err(format!("invalid wire {:?} in {:?}", wire, pattern)),
+        None => Ok(()),
+    }
+}
+
+// Bitmask of the wires lit in a pattern, wire 'a' being bit 0. Patterns must have
+// passed `check_pattern`.
+fn wire_mask(pattern: &str) -> u32 {
+    pattern
+        .bytes()
+        .fold(0, |mask, wire| mask | 1 << (wire - b'a'))
+}
+
+struct GlyphTable {
+    segments: usize,
+    glyphs: Vec<(char, u32)>,
+}
+
+impl GlyphTable {
+    fn from_pairs(pairs: &[(char, &str)]) -> GlyphTable {
+        let glyphs: Vec<(char, u32)> = pairs
+            .iter()
+            .map(|&(symbol, segments)| (symbol, wire_mask(segments)))
+            .collect();
+        let used = glyphs.iter().fold(0, |acc, &(_, mask)| acc | mask);
+        GlyphTable {
+            segments: (32 - used.leading_zeros()) as usize,
+            glyphs,
+        }
+    }
+
+    fn standard() -> GlyphTable {
+        GlyphTable::from_pairs(&[
+            ('0', "abcefg"),
+            ('1', "cf"),
+            ('2', "acdeg"),
+            ('3', "acdfg"),
+            ('4', "bcdf"),
+            ('5', "abdfg"),
+            ('6', "abdefg"),
+            ('7', "acf"),
+            ('8', "abcdefg"),
+            ('9', "abcdfg"),
+        ])
+    }
+
+    fn hex() -> GlyphTable {
+        let mut table = GlyphTable::standard();
+        table.glyphs.extend(
+            GlyphTable::from_pairs(&[
+                ('A', "abcdef"),
+                ('b', "bdefg"),
+                ('C', "abeg"),
+                ('d', "cdefg"),
+                ('E', "abdeg"),
+                ('F', "abde"),
+            ])
+            .glyphs,
+        );
+        table
+    }
+
+    // One glyph per line as `<symbol> <segments>`, e.g. `7 acf`
+    fn from_str(input: &str) -> Result<GlyphTable, String> {
+        let mut pairs: Vec<(char, &str)> = Vec::new();
+        for line in input.lines().filter(|line| !line.trim().is_empty()) {
+            let mut parts = line.split_whitespace();
+            let symbol = parts.next().unwrap().chars().next().unwrap();
+            let segments = parts
+                .next()
+                .ok_or_else(|| format!("glyph {:?} has no segments", symbol))?;
+            check_pattern(segments)?;
+            pairs.push((symbol, segments));
+        }
+        if pairs.is_empty() {
+            return Err("glyph table is empty".to_string());
+        }
+        Ok(GlyphTable::from_pairs(&pairs))
+    }
+
+    fn symbol(&self, segments: u32) -> Option<char> {
+        self.glyphs
+            .iter()
+            .find(|&&(_, mask)| mask == segments)
+            .map(|&(symbol, _)| symbol)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Decoding {
+    Unique(HashMap<u32, char>),
+    // Number of distinct decodings consistent with the patterns
+    Ambiguous(usize),
+    Impossible,
+}
+
+fn apply_permutation(wires: u32, permutation: &[usize]) -> u32 {
+    permutation
+        .iter()
+        .enumerate()
+        .filter(|&(wire, _)| wires & 1 << wire != 0)
+        .fold(0, |acc, (_, &segment)| acc | 1 << segment)
+}
+
+// Backtracks over wire -> segment assignments. A partial assignment survives only if every
+// pattern can still become some glyph of the same size: its assigned wires must land on
+// that glyph, and wires assigned outside the pattern must land off it.
+fn search_permutations(
+    table: &GlyphTable,
+    patterns: &[u32],
+    permutation: &mut Vec<usize>,
+    used: u32,
+    found: &mut Vec<Vec<usize>>,
+) {
+    let assigned_wires = (1u32 << permutation.len()) - 1;
+    let consistent = patterns.iter().all(|&pattern| {
+        let inside = apply_permutation(pattern & assigned_wires, permutation);
+        let outside = apply_permutation(!pattern & assigned_wires, permutation);
+        table.glyphs.iter().any(|&(_, glyph)| {
+            glyph.count_ones() == pattern.count_ones()
+                && glyph & inside == inside
+                && glyph & outside == 0
+        })
+    });
+    if !consistent {
+        return;
+    }
+    if permutation.len() == table.segments {
+        found.push(permutation.clone());
+        return;
+    }
+
+    for segment in 0..table.segments {
+        if used & 1 << segment == 0 {
+            permutation.push(segment);
+            search_permutations(table, patterns, permutation, used | 1 << segment, found);
+            permutation.pop();
+        }
+    }
+}
+
+fn solve_wiring(table: &GlyphTable, patterns: &[&str]) -> Decoding {
+    let masks: Vec<u32> = patterns.iter().map(|&pattern| wire_mask(pattern)).collect();
+    let mut found = Vec::new();
+    search_permutations(table, &masks, &mut Vec::new(), 0, &mut found);
+
+    let mut decodings: Vec<Vec<(u32, char)>> = found
+        .iter()
+        .filter_map(|permutation| {
+            let mut decoding: Vec<(u32, char)> = masks
+                .iter()
+                .map(|&mask| {
+                    table
+                        .symbol(apply_permutation(mask, permutation))
+                        .map(|symbol| (mask, symbol))
+                })
+                .collect::<Option<_>>()?;
+            decoding.sort_unstable();
+            decoding.dedup();
+            Some(decoding)
+        })
+        .collect();
+    decodings.sort_unstable();
+    decodings.dedup();
+
+    match decodings.len() {
+        0 => Decoding::Impossible,
+        1 => Decoding::Unique(decodings.pop().unwrap().into_iter().collect()),
+        count => Decoding::Ambiguous(count),
+    }
+}
+
+fn permutations(n: usize) -> Vec<Vec<usize>> {
+    if n == 0 {
+        return vec![vec![]];
+    }
+    permutations(n - 1)
+        .into_iter()
+        .flat_map(|smaller| {
+            (0..n).map(move |position| {
+                let mut permutation = smaller.clone();
+                permutation.insert(position, n - 1);
+                permutation
+            })
+        })
+        .collect()
+}
+
+// Glyphs nearest to the segments in Hamming distance, as (symbol, best, runner-up distance)
+fn nearest_glyph(table: &GlyphTable, segments: u32) -> (char, u32, u32) {
+    let mut distances: Vec<(u32, char)> = table
+        .glyphs
+        .iter()
+        .map(|&(symbol, glyph)| ((glyph ^ segments).count_ones(), symbol))
+        .collect();
+    distances.sort_unstable();
+    let runner_up = distances.get(1).map_or(u32::MAX, |&(distance, _)| distance);
+    (distances[0].1, distances[0].0, runner_up)
+}
+
+#[derive(Debug)]
+struct NoisyDigit {
+    symbol: char,
+    // 1.0 for an exact, unambiguous match, 0.0 when another glyph fits just as well
+    confidence: f64,
+}
+
+const MIN_CONFIDENCE: f64 = 0.5;
+
+// Picks the wirings that minimize the total Hamming distance between every pattern and its
+// nearest glyph, so dead or stuck segments cost a little instead of failing the decode.
+// Each output's confidence is its margin over the runner-up glyph, scaled by how many of
+// the equally good wirings agree on the symbol.
+fn decode_tolerant(table: &GlyphTable, patterns: &[&str], outputs: &[&str]) -> Vec<NoisyDigit> {
+    let masks: Vec<u32> = patterns
+        .iter()
+        .chain(outputs)
+        .map(|&pattern| wire_mask(pattern))
+        .collect();
+
+    let mut best_cost = u32::MAX;
+    let mut best: Vec<Vec<usize>> = Vec::new();
+    for permutation in permutations(table.segments) {
+        let cost: u32 = masks
+            .iter()
+            .map(|&mask| nearest_glyph(table, apply_permutation(mask, &permutation)).1)
+            .sum();
+        if cost < best_cost {
+            best_cost = cost;
+            best.clear();
+        }
+        if cost == best_cost {
+            best.push(permutation);
+        }
+    }
+
+    outputs
+        .iter()
+        .map(|&output| {
+            let mask = wire_mask(output);
+            let readings: Vec<(char, u32, u32)> = best
+                .iter()
+                .map(|permutation| nearest_glyph(table, apply_permutation(mask, permutation)))
+                .collect();
+            let (symbol, distance, runner_up) = readings[0];
+            let agreeing = readings
+                .iter()
+                .filter(|&&(other, _, _)| other == symbol)
+                .count();
+            let margin = if runner_up == u32::MAX {
+                1.0
+            } else {
+                (runner_up - distance) as f64 / runner_up.max(1) as f64
+            };
+            NoisyDigit {
+                symbol,
+                confidence: margin * agreeing as f64 / readings.len() as f64,
+            }
+        })
+        .collect()
+}
+
+// xorshift64* generator, enough to scramble displays reproducibly without a dependency
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for index in (1..items.len()).rev() {
+            items.swap(index, self.below(index + 1));
+        }
+    }
+}
+
+// Wires that light the given segments when wire w drives segment permutation[w],
+// written in a random order
+fn scramble_glyph(segments: u32, permutation: &[usize], rng: &mut Rng) -> String {
+    let mut wires: Vec<char> = permutation
+        .iter()
+        .enumerate()
+        .filter(|&(_, &segment)| segments & 1 << segment != 0)
+        .map(|(wire, _)| (b'a' + wire as u8) as char)
+        .collect();
+    rng.shuffle(&mut wires);
+    wires.into_iter().collect()
+}
+
+// Inverse of decoding: renders every glyph and the output symbols through the wiring
+// as a `patterns | outputs` line
+fn scramble_entry(
+    table: &GlyphTable,
+    permutation: &[usize],
+    outputs: &str,
+    rng: &mut Rng,
+) -> String {
+    let mut glyphs: Vec<u32> = table.glyphs.iter().map(|&(_, mask)| mask).collect();
+    rng.shuffle(&mut glyphs);
+    let patterns: Vec<String> = glyphs
+        .iter()
+        .map(|&glyph| scramble_glyph(glyph, permutation, rng))
+        .collect();
+    let digits: Vec<String> = outputs
+        .chars()
+        .map(|symbol| {
+            let &(_, glyph) = table
+                .glyphs
+                .iter()
+                .find(|&&(other, _)| other == symbol)
+                .expect("symbol missing from glyph table");
+            scramble_glyph(glyph, permutation, rng)
+        })
+        .collect();
+    format!("{} | {}", patterns.join(" "), digits.join(" "))
+}
+
+fn random_entry(table: &GlyphTable, rng: &mut Rng) -> (String, String) {
+    let mut permutation: Vec<usize> = (0..table.segments).collect();
+    rng.shuffle(&mut permutation);
+    let outputs: String = (0..4)
+        .map(|_| table.glyphs[rng.below(table.glyphs.len())].0)
+        .collect();
+    let line = scramble_entry(table, &permutation, &outputs, rng);
+    (line, outputs)
+}
+
+// Generates scrambled entries and checks that decoding recovers the original symbols
+fn fuzz_round_trip(table: &GlyphTable, entries: usize, rng: &mut Rng) {
+    // construct_mappings only knows the standard digits
+    let is_standard = table.glyphs == GlyphTable::standard().glyphs;
+    for _ in 0..entries {
+        let (line, outputs) = random_entry(table, rng);
+        let (patterns, digits) = parse_entry(&line).expect("generated an invalid entry");
+
+        let decoded: String = match solve_wiring(table, &patterns) {
+            Decoding::Unique(mappings) => digits
+                .iter()
+                .map(|&digit| mappings[&wire_mask(digit)])
+                .collect(),
+            other => panic!("{} did not decode uniquely: {:?}", line, other),
+        };
+        assert_eq!(decoded, outputs, "{}", line);
+
+        let tolerant: String = decode_tolerant(table, &patterns, &digits)
+            .iter()
+            .map(|digit| digit.symbol)
+            .collect();
+        assert_eq!(tolerant, outputs, "{}", line);
+
+        if is_standard {
+            let mappings = construct_mappings(&patterns).expect("standard digits should decode");
+            let decoded: String = digits
+                .iter()
+                .map(|&digit| {
+                    let value = mappings[&alphabetize(digit)];
+                    char::from_digit(value as u32, 10).unwrap()
+                })
+                .collect();
+            assert_eq!(decoded, outputs, "{}", line);
+        }
+    }
+}
+
+type Entry<'a> = (Vec<&'a str>, Vec<&'a str>);
+
+// Splits a `patterns | outputs` line, rejecting wires outside a to z
+fn parse_entry(line: &str) -> Result<Entry<'_>, String> {
+    let (patterns, outputs) = line
+        .split_once(" | ")
+        .ok_or_else(|| format!("missing ' | ' in {:?}", line))?;
+    let patterns: Vec<&str> = patterns.split_whitespace().collect();
+    let outputs: Vec<&str> = outputs.split_whitespace().collect();
+    for pattern in patterns.iter().chain(&outputs) {
+        check_pattern(pattern)?;
+    }
+    Ok((patterns, outputs))
 }
 
 fn main() {
-    let contents = include_str!("day8.txt");
+    // `--input <file>` reads other entries. `--glyphs hex` or `--glyphs <file>` decodes
+    // every entry against another glyph table, and `--tolerant` switches to nearest-glyph
+    // decoding for displays with faulty segments. `--generate n` prints n scrambled entries
+    // for the table and `--fuzz n` round-trips n of them through the decoders, both seeded
+    // by `--seed`.
+    let args: Vec<String> = env::args().collect();
+    let input = args.iter().position(|arg| arg == "--input").map(|index| {
+        let path = args.get(index + 1).expect("--input needs a file");
+        fs::read_to_string(path).expect("failed to read input")
+    });
+    let contents = match &input {
+        Some(input) => input.trim_end(),
+        None => include_str!("day8.txt"),
+    };
 
     let rows: Vec<&str> = contents.split('\n').collect();
 
-    let parsed_rows: Vec<Vec<Vec<&str>>> = rows
+    let parsed_rows: Vec<Entry> = rows
         .iter()
-        .map(|&row| {
-            row.split(" | ")
-                .map(|side| side.split_whitespace().collect::<Vec<_>>())
-                .collect::<Vec<_>>()
+        .enumerate()
+        .map(|(index, &row)| {
+            parse_entry(row).unwrap_or_else(|err| panic!("line {}: {}", index + 1, err))
         })
         .collect();
 
-    let wires: Vec<&Vec<&str>> = parsed_rows.iter().map(|row| row.first().unwrap()).collect();
-    let digits_vec: Vec<&Vec<&str>> = parsed_rows.iter().map(|row| row.last().unwrap()).collect();
+    let wires: Vec<&Vec<&str>> = parsed_rows.iter().map(|row| &row.0).collect();
+    let digits_vec: Vec<&Vec<&str>> = parsed_rows.iter().map(|row| &row.1).collect();
 
     let part_1 = digits_vec
         .iter()
@@ -96,18 +500,158 @@ fn main() {
 
     println!("Part 1: {:?}", part_1);
 
-    let part_2: usize = wires
+    let part_2: Option<usize> = wires
         .iter()
         .zip(digits_vec.iter())
         .map(|(&wire, &digits)| {
-            let mappings = construct_mappings(wire);
-            let value = digits
-                .iter()
-                .map(|&digit| mappings.get(&alphabetize(digit)).unwrap())
-                .fold(0, |acc, ele| acc * 10 + ele);
-            value
+            let mappings = construct_mappings(wire)?;
+            digits.iter().try_fold(0, |acc, &digit| {
+                Some(acc * 10 + mappings.get(&alphabetize(digit))?)
+            })
         })
         .sum();
 
-    println!("Part 2: {:?}", part_2);
+    match part_2 {
+        Some(part_2) => println!("Part 2: {:?}", part_2),
+        None => println!("Part 2: some entries are not standard digits"),
+    }
+
+    let count = |name: &str| {
+        args.iter().position(|arg| arg == name).map(|index| {
+            args.get(index + 1)
+                .and_then(|value| value.parse::<u64>().ok())
+                .expect("expected a number")
+        })
+    };
+    let tolerant = args.iter().any(|arg| arg == "--tolerant");
+    let glyphs = args.iter().position(|arg| arg == "--glyphs").map(|index| {
+        args.get(index + 1)
+            .expect("--glyphs needs a table")
+            .as_str()
+    });
+    let table = match glyphs {
+        None | Some("standard") => GlyphTable::standard(),
+        Some("hex") => GlyphTable::hex(),
+        Some(path) => {
+            GlyphTable::from_str(&fs::read_to_string(path).expect("failed to read glyphs"))
+                .unwrap_or_else(|err| panic!("invalid glyph table: {}", err))
+        }
+    };
+    let mut rng = Rng::new(count("--seed").unwrap_or(2021));
+    if let Some(entries) = count("--generate") {
+        for _ in 0..entries {
+            println!("{}", random_entry(&table, &mut rng).0);
+        }
+    }
+    if let Some(entries) = count("--fuzz") {
+        fuzz_round_trip(&table, entries as usize, &mut rng);
+        println!("Round-tripped {} scrambled entries", entries);
+    }
+    if tolerant {
+        for (entry, (&wire, &digits)) in wires.iter().zip(digits_vec.iter()).enumerate() {
+            let decoded = decode_tolerant(&table, wire, digits);
+            let value: String = decoded.iter().map(|digit| digit.symbol).collect();
+            let confidences: Vec<String> = decoded
+                .iter()
+                .map(|digit| format!("{:.2}", digit.confidence))
+                .collect();
+            let flag = if decoded
+                .iter()
+                .any(|digit| digit.confidence < MIN_CONFIDENCE)
+            {
+                " (low confidence)"
+            } else {
+                ""
+            };
+            println!(
+                "{}: {} [{}]{}",
+                entry + 1,
+                value,
+                confidences.join(" "),
+                flag
+            );
+        }
+    } else if glyphs.is_some() {
+        for (entry, (&wire, &digits)) in wires.iter().zip(digits_vec.iter()).enumerate() {
+            let patterns: Vec<&str> = wire.iter().chain(digits.iter()).copied().collect();
+            match solve_wiring(&table, &patterns) {
+                Decoding::Unique(mappings) => {
+                    let value: String = digits
+                        .iter()
+                        .map(|&digit| mappings[&wire_mask(digit)])
+                        .collect();
+                    println!("{}: {}", entry + 1, value);
+                }
+                Decoding::Ambiguous(count) => {
+                    println!("{}: ambiguous ({} decodings)", entry + 1, count)
+                }
+                Decoding::Impossible => println!("{}: impossible", entry + 1),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solver_matches_construct_mappings() {
+        let standard = GlyphTable::standard();
+        for line in include_str!("day8.txt").split('\n') {
+            let (patterns, outputs) = parse_entry(line).unwrap();
+            let mappings = construct_mappings(&patterns).unwrap();
+            let Decoding::Unique(solved) = solve_wiring(&standard, &patterns) else {
+                panic!("{} did not decode uniquely", line);
+            };
+            for output in outputs {
+                let expected = char::from_digit(mappings[&alphabetize(output)] as u32, 10);
+                assert_eq!(Some(solved[&wire_mask(output)]), expected, "{}", line);
+            }
+        }
+    }
+
+    fn assert_round_trips(table: &GlyphTable, seed: u64) {
+        let mut rng = Rng::new(seed);
+        for _ in 0..50 {
+            let (line, outputs) = random_entry(table, &mut rng);
+            let (patterns, digits) = parse_entry(&line).unwrap();
+            let Decoding::Unique(mappings) = solve_wiring(table, &patterns) else {
+                panic!("{} did not decode uniquely", line);
+            };
+            let decoded: String = digits
+                .iter()
+                .map(|&digit| mappings[&wire_mask(digit)])
+                .collect();
+            assert_eq!(decoded, outputs, "{}", line);
+        }
+    }
+
+    #[test]
+    fn generated_entries_round_trip() {
+        assert_round_trips(&GlyphTable::standard(), 2021);
+        assert_round_trips(&GlyphTable::hex(), 7);
+    }
+
+    #[test]
+    fn fuzzes_custom_ten_glyph_tables() {
+        let table = GlyphTable::from_str(
+            "0 abcefg\n1 cf\n2 acdeg\n3 acdfg\n4 bcdf\n5 abdfg\n6 abdefg\n7 acf\nH bcefg\nL efg",
+        )
+        .unwrap();
+        assert_round_trips(&table, 1);
+        fuzz_round_trip(&table, 5, &mut Rng::new(3));
+    }
+
+    #[test]
+    fn rejects_invalid_wires() {
+        assert!(parse_entry("ab cA | ab").is_err());
+        assert!(parse_entry("ab cd | a1").is_err());
+        assert!(parse_entry("ab cd ab").is_err());
+        assert!(GlyphTable::from_str("1 cf\n7 aCf").is_err());
+        assert!(GlyphTable::from_str("1 cf\n7").is_err());
+        assert!(GlyphTable::from_str("").is_err());
+        assert!(GlyphTable::from_str("\n  \n").is_err());
+        assert!(GlyphTable::from_str("1 cf\n7 acf").is_ok());
+    }
 }
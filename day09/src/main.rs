@@ -1,4 +1,4 @@
-use std::collections::{HashSet, VecDeque};
+use std::env;
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 struct Coordinate {
@@ -44,29 +44,294 @@ fn get_valid_neighbors(grid: &[Vec<u32>], coord: &Coordinate) -> Vec<Coordinate>
     vec
 }
 
-fn get_basin_size(grid: &[Vec<u32>], start: Coordinate) -> usize {
-    let mut visited = HashSet::from([start]);
-    let mut deq = VecDeque::from([start]);
+fn find_low_points(grid: &[Vec<u32>]) -> Vec<Coordinate> {
+    let rows = grid.len();
+    let columns = grid[0].len();
+
+    let mut low_points = Vec::new();
+    for row in 0..rows {
+        for column in 0..columns {
+            let coord = Coordinate { row, column };
+            let valid_neighbors = get_valid_neighbors(grid, &coord);
 
-    let mut size: usize = 1;
-    while let Some(coord) = deq.pop_front() {
-        let current_val = get_grid_value(grid, &coord);
-        let neighbors = get_valid_neighbors(grid, &coord);
+            let current_val = get_grid_value(grid, &coord);
 
-        for neighbor in neighbors {
-            let neighbor_val = get_grid_value(grid, &neighbor);
-            if !visited.contains(&neighbor) && neighbor_val != 9 && neighbor_val > current_val {
-                deq.push_back(neighbor);
-                visited.insert(neighbor);
-                size += 1;
+            if !valid_neighbors
+                .iter()
+                .any(|neighbor| get_grid_value(grid, neighbor) <= current_val)
+            {
+                low_points.push(coord);
             }
         }
     }
+    low_points
+}
+
+struct DisjointSet {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(len: usize) -> DisjointSet {
+        DisjointSet {
+            parent: (0..len).collect(),
+            size: vec![1; len],
+        }
+    }
+
+    fn find(&mut self, node: usize) -> usize {
+        let mut root = node;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut current = node;
+        while self.parent[current] != root {
+            let next = self.parent[current];
+            self.parent[current] = root;
+            current = next;
+        }
+        root
+    }
+
+    fn union(&mut self, first: usize, second: usize) {
+        let (mut first, mut second) = (self.find(first), self.find(second));
+        if first == second {
+            return;
+        }
+        if self.size[first] < self.size[second] {
+            std::mem::swap(&mut first, &mut second);
+        }
+        self.parent[second] = first;
+        self.size[first] += self.size[second];
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Basin {
+    size: usize,
+    lowest: Coordinate,
+    // Height of the surrounding 9s above the lowest point
+    depth: u32,
+    top_left: Coordinate,
+    bottom_right: Coordinate,
+}
+
+struct BasinMap {
+    // Index into `basins` for every cell, None for the 9s between basins
+    labels: Vec<Vec<Option<usize>>>,
+    basins: Vec<Basin>,
+}
+
+// Labels every basin bounded by 9s in one pass, joining each cell with its upper and left
+// neighbors in a disjoint set
+fn label_basins(grid: &[Vec<u32>]) -> BasinMap {
+    let rows = grid.len();
+    let columns = grid[0].len();
+    let index = |row: usize, column: usize| row * columns + column;
+
+    let mut sets = DisjointSet::new(rows * columns);
+    for row in 0..rows {
+        for column in 0..columns {
+            if grid[row][column] == 9 {
+                continue;
+            }
+            if row > 0 && grid[row - 1][column] != 9 {
+                sets.union(index(row, column), index(row - 1, column));
+            }
+            if column > 0 && grid[row][column - 1] != 9 {
+                sets.union(index(row, column), index(row, column - 1));
+            }
+        }
+    }
+
+    let mut labels = vec![vec![None; columns]; rows];
+    let mut label_of_root: Vec<Option<usize>> = vec![None; rows * columns];
+    let mut basins: Vec<Basin> = Vec::new();
+    for row in 0..rows {
+        for column in 0..columns {
+            let height = grid[row][column];
+            if height == 9 {
+                continue;
+            }
+            let coord = Coordinate { row, column };
+            let root = sets.find(index(row, column));
+            let label = *label_of_root[root].get_or_insert_with(|| {
+                basins.push(Basin {
+                    size: 0,
+                    lowest: coord,
+                    depth: 0,
+                    top_left: coord,
+                    bottom_right: coord,
+                });
+                basins.len() - 1
+            });
+            labels[row][column] = Some(label);
+
+            let basin = &mut basins[label];
+            basin.size += 1;
+            if height < get_grid_value(grid, &basin.lowest) {
+                basin.lowest = coord;
+            }
+            basin.depth = 9 - get_grid_value(grid, &basin.lowest);
+            basin.top_left.column = basin.top_left.column.min(column);
+            basin.bottom_right.row = row;
+            basin.bottom_right.column = basin.bottom_right.column.max(column);
+        }
+    }
+
+    BasinMap { labels, basins }
+}
+
+struct Drainage {
+    // Lowest strictly lower neighbor, None where water pools
+    downstream: Vec<Vec<Option<Coordinate>>>,
+    // Where water falling on each cell ends up
+    outlet: Vec<Vec<Coordinate>>,
+    // Number of cells whose water flows through each cell, itself included
+    accumulation: Vec<Vec<usize>>,
+}
+
+// Routes rain from every cell by steepest descent. Cells are visited by height so each
+// cell's downstream neighbor is resolved before it (for outlets) or after it (for flow).
+fn simulate_drainage(grid: &[Vec<u32>]) -> Drainage {
+    let rows = grid.len();
+    let columns = grid[0].len();
+
+    let downstream: Vec<Vec<Option<Coordinate>>> = (0..rows)
+        .map(|row| {
+            (0..columns)
+                .map(|column| {
+                    let coord = Coordinate { row, column };
+                    let current_val = get_grid_value(grid, &coord);
+                    get_valid_neighbors(grid, &coord)
+                        .into_iter()
+                        .filter(|neighbor| get_grid_value(grid, neighbor) < current_val)
+                        .min_by_key(|neighbor| get_grid_value(grid, neighbor))
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut by_height: Vec<Coordinate> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| Coordinate { row, column }))
+        .collect();
+    by_height.sort_by_key(|coord| get_grid_value(grid, coord));
+
+    let mut outlet = vec![vec![Coordinate { row: 0, column: 0 }; columns]; rows];
+    for coord in &by_height {
+        outlet[coord.row][coord.column] = match downstream[coord.row][coord.column] {
+            Some(next) => outlet[next.row][next.column],
+            None => *coord,
+        };
+    }
+
+    let mut accumulation = vec![vec![1; columns]; rows];
+    for coord in by_height.iter().rev() {
+        if let Some(next) = downstream[coord.row][coord.column] {
+            accumulation[next.row][next.column] += accumulation[coord.row][coord.column];
+        }
+    }
 
-    size
+    Drainage {
+        downstream,
+        outlet,
+        accumulation,
+    }
+}
+
+// Cells with a neighbor that drains to a different outlet
+fn watershed_boundaries(grid: &[Vec<u32>], drainage: &Drainage) -> Vec<Vec<bool>> {
+    grid.iter()
+        .enumerate()
+        .map(|(row, values)| {
+            (0..values.len())
+                .map(|column| {
+                    let coord = Coordinate { row, column };
+                    get_valid_neighbors(grid, &coord).iter().any(|neighbor| {
+                        drainage.outlet[neighbor.row][neighbor.column]
+                            != drainage.outlet[row][column]
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Cells without a strictly lower neighbor, in reading order. Besides the low points these
+// include cells in flat areas, which keep their own rain.
+fn outlets(drainage: &Drainage) -> Vec<Coordinate> {
+    drainage
+        .downstream
+        .iter()
+        .enumerate()
+        .flat_map(|(row, cells)| {
+            cells
+                .iter()
+                .enumerate()
+                .filter(|(_, next)| next.is_none())
+                .map(move |(column, _)| Coordinate { row, column })
+        })
+        .collect()
+}
+
+// Prints how many cells drain into each outlet, then the watershed boundaries as '#'
+fn print_drainage(grid: &[Vec<u32>], low_points: &[Coordinate], drainage: &Drainage) {
+    for outlet in outlets(drainage) {
+        let kind = if low_points.contains(&outlet) {
+            "low point"
+        } else {
+            "flat outlet"
+        };
+        println!(
+            "{} {:?}: {} cells drain here",
+            kind,
+            (outlet.row, outlet.column),
+            drainage.accumulation[outlet.row][outlet.column]
+        );
+    }
+    for row in watershed_boundaries(grid, drainage) {
+        let line: String = row
+            .iter()
+            .map(|&boundary| if boundary { '#' } else { '.' })
+            .collect();
+        println!("{}", line);
+    }
+}
+
+// Prints each basin's statistics followed by the label grid, one base-36 character per
+// cell cycling through the labels, with '.' for the 9s
+fn print_basins(basin_map: &BasinMap) {
+    for (label, basin) in basin_map.basins.iter().enumerate() {
+        println!(
+            "basin {}: size {}, lowest {:?}, depth {}, rows {}..={}, columns {}..={}",
+            label,
+            basin.size,
+            (basin.lowest.row, basin.lowest.column),
+            basin.depth,
+            basin.top_left.row,
+            basin.bottom_right.row,
+            basin.top_left.column,
+            basin.bottom_right.column
+        );
+    }
+    for row in &basin_map.labels {
+        let line: String = row
+            .iter()
+            .map(|label| match label {
+                Some(label) => std::char::from_digit((label % 36) as u32, 36).unwrap(),
+                None => '.',
+            })
+            .collect();
+        println!("{}", line);
+    }
 }
 
 fn main() {
+    // `--basins` prints every basin and the label grid, `--drainage` the cells draining
+    // into each outlet and the watershed boundaries.
+    let args: Vec<String> = env::args().collect();
+    let flag = |name: &str| args.iter().any(|arg| arg == name);
     let contents = include_str!("day9.txt");
 
     let grid = contents
@@ -78,33 +343,125 @@ fn main() {
         })
         .collect::<Vec<_>>();
 
-    let rows = grid.len();
-    let columns = grid[0].len();
+    let low_points = find_low_points(&grid);
+    let risk: u32 = low_points
+        .iter()
+        .map(|coord| get_grid_value(&grid, coord) + 1)
+        .sum();
 
-    let mut basin_sizes: Vec<usize> = Vec::new();
-    let mut risk: u32 = 0;
+    println!("Part 1: {:?}", risk);
 
-    for row in 0..rows {
-        for column in 0..columns {
-            let coord = Coordinate { row, column };
-            let valid_neighbors = get_valid_neighbors(&grid, &coord);
+    let basin_map = label_basins(&grid);
+    let mut basin_sizes: Vec<usize> = basin_map.basins.iter().map(|basin| basin.size).collect();
+    basin_sizes.sort_unstable();
+    let basin_prod: usize = basin_sizes.iter().rev().take(3).product();
 
-            let current_val = get_grid_value(&grid, &coord);
+    println!("Part 2: {:?}", basin_prod);
 
-            if !valid_neighbors
-                .iter()
-                .any(|neighbor| get_grid_value(&grid, neighbor) <= current_val)
-            {
-                risk += current_val + 1;
-                basin_sizes.push(get_basin_size(&grid, coord));
+    if flag("--basins") {
+        print_basins(&basin_map);
+    }
+    if flag("--drainage") {
+        print_drainage(&grid, &low_points, &simulate_drainage(&grid));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "2199943210\n3987894921\n9856789892\n8767896789\n9899965678";
+
+    fn parse(contents: &str) -> Vec<Vec<u32>> {
+        contents
+            .lines()
+            .map(|row| row.chars().map(|ele| ele.to_digit(10).unwrap()).collect())
+            .collect()
+    }
+
+    fn at(row: usize, column: usize) -> Coordinate {
+        Coordinate { row, column }
+    }
+
+    #[test]
+    fn labels_example_basins() {
+        let grid = parse(EXAMPLE);
+        let basin_map = label_basins(&grid);
+
+        // (size, lowest, depth, top left, bottom right) in scan order of first cell
+        let expected = [
+            (3, at(0, 1), 8, at(0, 0), at(1, 1)),
+            (9, at(0, 9), 9, at(0, 5), at(2, 9)),
+            (14, at(2, 2), 4, at(1, 0), at(4, 5)),
+            (9, at(4, 6), 4, at(2, 5), at(4, 9)),
+        ];
+        assert_eq!(basin_map.basins.len(), expected.len());
+        for (basin, &(size, lowest, depth, top_left, bottom_right)) in
+            basin_map.basins.iter().zip(&expected)
+        {
+            assert_eq!(basin.size, size);
+            assert_eq!(basin.lowest, lowest);
+            assert_eq!(basin.depth, depth);
+            assert_eq!(basin.top_left, top_left);
+            assert_eq!(basin.bottom_right, bottom_right);
+        }
+
+        for (row, values) in grid.iter().enumerate() {
+            for (column, &height) in values.iter().enumerate() {
+                assert_eq!(basin_map.labels[row][column].is_none(), height == 9);
             }
         }
+        for (label, basin) in basin_map.basins.iter().enumerate() {
+            let cells = basin_map
+                .labels
+                .iter()
+                .flatten()
+                .filter(|&&cell| cell == Some(label))
+                .count();
+            assert_eq!(cells, basin.size);
+        }
+        assert_eq!(basin_map.labels[2][7], Some(3));
     }
 
-    println!("Part 1: {:?}", risk);
+    #[test]
+    fn drains_to_low_points_and_flat_outlets() {
+        let grid = parse(EXAMPLE);
+        let drainage = simulate_drainage(&grid);
+        let example_outlets = outlets(&drainage);
+        for low_point in find_low_points(&grid) {
+            assert!(example_outlets.contains(&low_point));
+        }
+        let drained: usize = example_outlets
+            .iter()
+            .map(|outlet| drainage.accumulation[outlet.row][outlet.column])
+            .sum();
+        assert_eq!(drained, 50);
 
-    basin_sizes.sort_unstable();
-    let basin_prod: usize = basin_sizes.iter().rev().take(3).product();
+        // The top-left 2 has no lower neighbor but is not a low point either
+        let grid = parse("22\n21");
+        let drainage = simulate_drainage(&grid);
+        assert_eq!(find_low_points(&grid), vec![at(1, 1)]);
+        assert_eq!(outlets(&drainage), vec![at(0, 0), at(1, 1)]);
+        assert_eq!(
+            drainage.downstream,
+            vec![vec![None, Some(at(1, 1))], vec![Some(at(1, 1)), None]]
+        );
+        assert_eq!(
+            drainage.outlet,
+            vec![vec![at(0, 0), at(1, 1)], vec![at(1, 1), at(1, 1)]]
+        );
+        assert_eq!(drainage.accumulation, vec![vec![1, 1], vec![1, 3]]);
+    }
 
-    println!("Part 2: {:?}", basin_prod);
+    #[test]
+    fn watersheds_split_flat_areas() {
+        let grid = parse("33321");
+        let drainage = simulate_drainage(&grid);
+        assert_eq!(outlets(&drainage), vec![at(0, 0), at(0, 1), at(0, 4)]);
+        assert_eq!(drainage.accumulation, vec![vec![1, 1, 1, 2, 3]]);
+        assert_eq!(
+            watershed_boundaries(&grid, &drainage),
+            vec![vec![true, true, true, false, false]]
+        );
+    }
 }
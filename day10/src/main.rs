@@ -1,76 +1,525 @@
-use std::collections::{HashSet, VecDeque};
+use std::{
+    collections::VecDeque,
+    env, fs,
+    io::{self, BufRead},
+    str,
+};
 
-use itertools::{Either, Itertools};
+#[derive(Debug, Clone, Copy)]
+struct Delimiter {
+    open: char,
+    close: char,
+    error_score: i64,
+    completion_score: i64,
+}
 
-fn calculate_scores(phrase: &[char]) -> Either<i64, i64> {
-    let open_symbols: HashSet<char> = HashSet::from(['(', '[', '{', '<']);
+struct Grammar {
+    delimiters: Vec<Delimiter>,
+}
 
-    let mut stack: VecDeque<char> = VecDeque::new();
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Diagnostic {
+    Corrupted {
+        // 1-based column of the first illegal character
+        column: usize,
+        found: char,
+        // Closer for the innermost open chunk, None when nothing was open
+        expected: Option<char>,
+        score: i64,
+    },
+    Incomplete {
+        completion: String,
+        score: i64,
+    },
+}
 
-    for symbol in phrase {
-        if open_symbols.contains(symbol) {
-            stack.push_back(*symbol);
-        } else {
-            match (symbol, stack.pop_back()) {
-                (')', Some(x)) => {
-                    if x != '(' {
-                        return Either::Left(3);
-                    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Repair {
+    edits: usize,
+    text: String,
+}
+
+#[derive(Clone, Copy)]
+enum RepairStep {
+    Empty,
+    // Character outside the grammar, kept as is
+    Keep,
+    // Give the first character a new partner right beside its chunk
+    Insert,
+    // Pair the first character with the one at this index, possibly substituting either
+    Pair(usize, Delimiter),
+}
+
+impl Grammar {
+    fn standard() -> Grammar {
+        Grammar::from_str("( ) 3 1\n[ ] 57 2\n{ } 1197 3\n< > 25137 4")
+    }
+
+    // One delimiter per line as `<open> <close> <error score> <completion score>`
+    fn from_str(input: &str) -> Grammar {
+        let delimiters = input
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let symbol = |index: usize| fields[index].chars().next().unwrap();
+                let score = |index: usize| fields[index].parse::<i64>().expect("invalid score");
+                Delimiter {
+                    open: symbol(0),
+                    close: symbol(1),
+                    error_score: score(2),
+                    completion_score: score(3),
+                }
+            })
+            .collect();
+        Grammar { delimiters }
+    }
+
+    fn opening(&self, symbol: char) -> Option<&Delimiter> {
+        self.delimiters.iter().find(|ele| ele.open == symbol)
+    }
+
+    fn closing(&self, symbol: char) -> Option<&Delimiter> {
+        self.delimiters.iter().find(|ele| ele.close == symbol)
+    }
+
+    // None for a balanced phrase. Characters outside the grammar are skipped.
+    fn check(&self, phrase: &[char]) -> Option<Diagnostic> {
+        let mut linter = Linter::new(self);
+        for &symbol in phrase {
+            if let Some(diagnostic) = linter.feed(symbol) {
+                return Some(diagnostic);
+            }
+        }
+        linter.finish()
+    }
+}
+
+// Checks characters one at a time, holding nothing but the stack of open chunks
+struct Linter<'a> {
+    grammar: &'a Grammar,
+    stack: VecDeque<&'a Delimiter>,
+    column: usize,
+}
+
+impl<'a> Linter<'a> {
+    fn new(grammar: &'a Grammar) -> Linter<'a> {
+        Linter {
+            grammar,
+            stack: VecDeque::new(),
+            column: 0,
+        }
+    }
+
+    // Returns a diagnostic when the character is illegal
+    fn feed(&mut self, symbol: char) -> Option<Diagnostic> {
+        self.column += 1;
+        if let Some(delimiter) = self.grammar.opening(symbol) {
+            self.stack.push_back(delimiter);
+        } else if let Some(delimiter) = self.grammar.closing(symbol) {
+            match self.stack.pop_back() {
+                Some(open) if open.close == symbol => (),
+                open => {
+                    return Some(Diagnostic::Corrupted {
+                        column: self.column,
+                        found: symbol,
+                        expected: open.map(|open| open.close),
+                        score: delimiter.error_score,
+                    })
                 }
-                (']', Some(x)) => {
-                    if x != '[' {
-                        return Either::Left(57);
+            }
+        }
+        None
+    }
+
+    // Completion for whatever is still open, or None when nothing is, leaving the linter
+    // ready for a new phrase
+    fn finish(&mut self) -> Option<Diagnostic> {
+        let diagnostic = (!self.stack.is_empty()).then(|| Diagnostic::Incomplete {
+            completion: self.stack.iter().rev().map(|open| open.close).collect(),
+            score: self
+                .stack
+                .iter()
+                .rev()
+                .fold(0, |acc, open| acc * 5 + open.completion_score),
+        });
+        self.reset();
+        diagnostic
+    }
+
+    fn reset(&mut self) {
+        self.stack.clear();
+        self.column = 0;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StreamMode {
+    // Every line is a separate phrase, as in the puzzle input
+    Lines,
+    // The whole input is one phrase with newlines ignored. Linting resumes with an empty
+    // stack after each illegal character.
+    Continuous,
+}
+
+// Lints a reader chunk by chunk, passing each diagnostic and its 1-based line to `emit`
+// as soon as it is known
+fn lint_stream(
+    grammar: &Grammar,
+    mut reader: impl BufRead,
+    mode: StreamMode,
+    mut emit: impl FnMut(usize, Diagnostic),
+) -> io::Result<()> {
+    let mut linter = Linter::new(grammar);
+    // Line of the last symbol read, only advanced once the next line has a symbol so a
+    // trailing newline does not count as a line
+    let mut line = 0;
+    let mut line_start = true;
+    let mut skipping_line = false;
+    // Bytes of a UTF-8 character split across two chunks
+    let mut pending: Vec<u8> = Vec::new();
+
+    loop {
+        let chunk = reader.fill_buf()?;
+        if chunk.is_empty() {
+            break;
+        }
+        pending.extend_from_slice(chunk);
+        let consumed = chunk.len();
+        reader.consume(consumed);
+
+        let valid = match str::from_utf8(&pending) {
+            Ok(text) => text.len(),
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            Err(error) => return Err(io::Error::new(io::ErrorKind::InvalidData, error)),
+        };
+        for symbol in str::from_utf8(&pending[..valid]).unwrap().chars() {
+            if line_start {
+                line += 1;
+                line_start = false;
+            }
+            if symbol == '\n' {
+                if mode == StreamMode::Lines {
+                    // Blank and balanced lines leave nothing open, so they emit nothing
+                    if let Some(diagnostic) = linter.finish().filter(|_| !skipping_line) {
+                        emit(line, diagnostic);
                     }
+                    skipping_line = false;
                 }
-                ('}', Some(x)) => {
-                    if x != '{' {
-                        return Either::Left(1197);
+                linter.column = 0;
+                line_start = true;
+            } else if !skipping_line {
+                if let Some(diagnostic) = linter.feed(symbol) {
+                    emit(line, diagnostic);
+                    match mode {
+                        StreamMode::Lines => skipping_line = true,
+                        StreamMode::Continuous => linter.stack.clear(),
                     }
                 }
-                ('>', Some(x)) => {
-                    if x != '<' {
-                        return Either::Left(25137);
+            }
+        }
+        pending.drain(..valid);
+    }
+
+    // The last line may lack a newline; an empty input leaves nothing open
+    if let Some(diagnostic) = linter.finish().filter(|_| !skipping_line) {
+        emit(line, diagnostic);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct LintSummary {
+    syntax_score: i64,
+    completion_scores: Vec<i64>,
+}
+
+impl LintSummary {
+    fn record(&mut self, diagnostic: &Diagnostic) {
+        match diagnostic {
+            Diagnostic::Corrupted { score, .. } => self.syntax_score += score,
+            Diagnostic::Incomplete { score, .. } => self.completion_scores.push(*score),
+        }
+    }
+
+    // Middle completion score by quickselect rather than a full sort
+    fn median_completion(&mut self) -> Option<i64> {
+        if self.completion_scores.is_empty() {
+            return None;
+        }
+        let middle = self.completion_scores.len() / 2;
+        Some(*self.completion_scores.select_nth_unstable(middle).1)
+    }
+}
+
+fn lint_summary(
+    grammar: &Grammar,
+    reader: impl BufRead,
+    mode: StreamMode,
+    verbose: bool,
+) -> io::Result<LintSummary> {
+    let mut summary = LintSummary::default();
+    lint_stream(grammar, reader, mode, |line, diagnostic| {
+        if verbose {
+            print_diagnostic(line, &diagnostic);
+        }
+        summary.record(&diagnostic);
+    })?;
+    Ok(summary)
+}
+
+impl Grammar {
+    fn is_symbol(&self, symbol: char) -> bool {
+        self.opening(symbol).is_some() || self.closing(symbol).is_some()
+    }
+
+    // Cheapest delimiter to turn `first` and `second` into an open/close pair
+    fn pair_cost(&self, first: char, second: char) -> (usize, Delimiter) {
+        self.delimiters
+            .iter()
+            .map(|&delimiter| {
+                let cost =
+                    (first != delimiter.open) as usize + (second != delimiter.close) as usize;
+                (cost, delimiter)
+            })
+            .min_by_key(|&(cost, _)| cost)
+            .unwrap()
+    }
+
+    // Fewest insertions, deletions or substitutions that balance the phrase, by interval
+    // DP over phrase[start..end]. Deleting a stray character costs the same as inserting its
+    // partner, so repairs always insert and never drop any of the original text.
+    fn repair(&self, phrase: &[char]) -> Repair {
+        let n = phrase.len();
+        let mut cost = vec![vec![0usize; n + 1]; n + 1];
+        let mut steps = vec![vec![RepairStep::Empty; n + 1]; n + 1];
+
+        for len in 1..=n {
+            for start in 0..=n - len {
+                let end = start + len;
+                let first = phrase[start];
+                if !self.is_symbol(first) {
+                    cost[start][end] = cost[start + 1][end];
+                    steps[start][end] = RepairStep::Keep;
+                    continue;
+                }
+
+                let mut best = (1 + cost[start + 1][end], RepairStep::Insert);
+                for partner in start + 1..end {
+                    if !self.is_symbol(phrase[partner]) {
+                        continue;
+                    }
+                    let (edits, delimiter) = self.pair_cost(first, phrase[partner]);
+                    let total = edits + cost[start + 1][partner] + cost[partner + 1][end];
+                    if total < best.0 || (total == best.0 && edits == 0) {
+                        best = (total, RepairStep::Pair(partner, delimiter));
                     }
                 }
-                _ => (),
+                cost[start][end] = best.0;
+                steps[start][end] = best.1;
             }
         }
+
+        let mut text = String::new();
+        self.rebuild(phrase, &steps, 0, n, &mut text);
+        Repair {
+            edits: cost[0][n],
+            text,
+        }
     }
 
-    let completion_score = stack
-        .iter()
-        .rev()
-        .map(|ele| match ele {
-            '(' => 1,
-            '[' => 2,
-            '{' => 3,
-            '<' => 4,
-            _ => panic!("unexpected"),
-        })
-        .fold(0, |acc, value| acc * 5 + value);
+    fn rebuild(
+        &self,
+        phrase: &[char],
+        steps: &[Vec<RepairStep>],
+        start: usize,
+        end: usize,
+        text: &mut String,
+    ) {
+        match steps[start][end] {
+            RepairStep::Empty => (),
+            RepairStep::Keep => {
+                text.push(phrase[start]);
+                self.rebuild(phrase, steps, start + 1, end, text);
+            }
+            RepairStep::Insert => match self.opening(phrase[start]) {
+                Some(delimiter) => {
+                    text.push(delimiter.open);
+                    self.rebuild(phrase, steps, start + 1, end, text);
+                    text.push(delimiter.close);
+                }
+                None => {
+                    let delimiter = self.closing(phrase[start]).unwrap();
+                    text.push(delimiter.open);
+                    text.push(delimiter.close);
+                    self.rebuild(phrase, steps, start + 1, end, text);
+                }
+            },
+            RepairStep::Pair(partner, delimiter) => {
+                text.push(delimiter.open);
+                self.rebuild(phrase, steps, start + 1, partner, text);
+                text.push(delimiter.close);
+                self.rebuild(phrase, steps, partner + 1, end, text);
+            }
+        }
+    }
+}
 
-    Either::Right(completion_score)
+fn print_diagnostic(line: usize, diagnostic: &Diagnostic) {
+    match diagnostic {
+        Diagnostic::Corrupted {
+            column,
+            found,
+            expected: Some(expected),
+            ..
+        } => println!(
+            "{}:{}: expected '{}', found '{}'",
+            line, column, expected, found
+        ),
+        Diagnostic::Corrupted {
+            column,
+            found,
+            expected: None,
+            ..
+        } => println!("{}:{}: unexpected '{}'", line, column, found),
+        Diagnostic::Incomplete { completion, .. } => {
+            println!("{}: incomplete, complete with '{}'", line, completion)
+        }
+    }
 }
 
 fn main() {
     let contents = include_str!("day10.txt");
 
-    let phrases: Vec<Vec<char>> = contents
-        .split('\n')
-        .map(|line| line.chars().collect::<Vec<_>>())
-        .collect();
+    // `--grammar <file>` swaps in another delimiter table, `--diagnostics` reports
+    // every line and `--repair` suggests a minimal fix for every corrupted line.
+    // `--lint <file>` (or `-` for stdin) streams another input through the linter,
+    // line by line or as one phrase with `--continuous`.
+    let args: Vec<String> = env::args().collect();
+    let grammar = match args.iter().position(|arg| arg == "--grammar") {
+        Some(index) => Grammar::from_str(
+            &fs::read_to_string(args.get(index + 1).expect("--grammar needs a file"))
+                .expect("failed to read grammar"),
+        ),
+        None => Grammar::standard(),
+    };
 
-    let (syntax_error_scores, mut completion_scores): (Vec<i64>, Vec<i64>) = phrases
-        .iter()
-        .map(|phrase| calculate_scores(phrase))
-        .partition_map(|val| val);
+    let verbose = args.iter().any(|arg| arg == "--diagnostics");
+    let mut summary = lint_summary(&grammar, contents.as_bytes(), StreamMode::Lines, verbose)
+        .expect("failed to lint input");
 
-    let syntax_score: i64 = syntax_error_scores.iter().sum();
+    if args.iter().any(|arg| arg == "--repair") {
+        for (line, phrase) in contents.split('\n').enumerate() {
+            let phrase: Vec<char> = phrase.chars().collect();
+            if let Some(Diagnostic::Corrupted { .. }) = grammar.check(&phrase) {
+                let repair = grammar.repair(&phrase);
+                println!("{}: {} edits: {}", line + 1, repair.edits, repair.text);
+            }
+        }
+    }
 
-    println!("Part 1: {:?}", syntax_score);
+    println!("Part 1: {:?}", summary.syntax_score);
 
-    completion_scores.sort_unstable();
-    let completion_score = completion_scores[completion_scores.len() / 2];
+    let completion_score = summary.median_completion().expect("no incomplete lines");
 
     println!("Part 2: {:?}", completion_score);
+
+    if let Some(index) = args.iter().position(|arg| arg == "--lint") {
+        let path = args.get(index + 1).expect("--lint needs a file or -");
+        let mode = if args.iter().any(|arg| arg == "--continuous") {
+            StreamMode::Continuous
+        } else {
+            StreamMode::Lines
+        };
+        let mut summary = if path == "-" {
+            lint_summary(&grammar, io::stdin().lock(), mode, true)
+        } else {
+            let file = fs::File::open(path).expect("failed to open input");
+            lint_summary(&grammar, io::BufReader::new(file), mode, true)
+        }
+        .expect("failed to lint input");
+        let median = summary.median_completion();
+        println!(
+            "syntax score {}, middle completion score {:?}",
+            summary.syntax_score, median
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(phrase: &str) -> Option<Diagnostic> {
+        Grammar::standard().check(&phrase.chars().collect::<Vec<_>>())
+    }
+
+    fn summarize(input: &str, mode: StreamMode) -> LintSummary {
+        lint_summary(&Grammar::standard(), input.as_bytes(), mode, false).unwrap()
+    }
+
+    #[test]
+    fn stream_skips_blank_and_balanced_lines() {
+        let mut summary = summarize("()\n\n(((\n", StreamMode::Lines);
+        assert_eq!(summary.completion_scores, vec![31]);
+        assert_eq!(summary.median_completion(), Some(31));
+
+        let mut summary = summarize("(]\n[]\n<", StreamMode::Lines);
+        assert_eq!(summary.syntax_score, 57);
+        assert_eq!(summary.median_completion(), Some(4));
+    }
+
+    #[test]
+    fn continuous_stream_emits_only_open_chunks() {
+        let mut summary = summarize("", StreamMode::Continuous);
+        assert_eq!(summary.median_completion(), None);
+
+        let mut summary = summarize("(\n[]\n", StreamMode::Continuous);
+        assert_eq!(summary.completion_scores, vec![1]);
+        assert_eq!(summary.median_completion(), Some(1));
+
+        let summary = summarize("(\n)\n", StreamMode::Continuous);
+        assert!(summary.completion_scores.is_empty());
+
+        // The final incomplete chunk is reported on the last line read, trailing newline or not
+        for input in ["(\n[\n", "(\n[", "(\n[\n\n"] {
+            let mut lines = Vec::new();
+            lint_stream(
+                &Grammar::standard(),
+                input.as_bytes(),
+                StreamMode::Continuous,
+                |line, _| lines.push(line),
+            )
+            .unwrap();
+            let expected = if input.ends_with("\n\n") { 3 } else { 2 };
+            assert_eq!(lines, vec![expected], "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn balanced_phrases_have_no_diagnostic() {
+        assert_eq!(check(""), None);
+        assert_eq!(check("()"), None);
+        assert_eq!(check("[<>({}){}[([])<>]]"), None);
+    }
+
+    #[test]
+    fn reports_corrupted_and_incomplete_phrases() {
+        assert_eq!(
+            check("(]"),
+            Some(Diagnostic::Corrupted {
+                column: 2,
+                found: ']',
+                expected: Some(')'),
+                score: 57,
+            })
+        );
+        assert_eq!(
+            check("[({(<(())[]>[[{[]{<()<>>"),
+            Some(Diagnostic::Incomplete {
+                completion: "}}]])})]".to_string(),
+                score: 288957,
+            })
+        );
+    }
 }
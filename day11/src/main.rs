@@ -1,4 +1,9 @@
-use std::collections::{HashSet, VecDeque};
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet, VecDeque},
+    env, fs,
+    hash::{Hash, Hasher},
+    io::{self, Write},
+};
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 struct Coordinate {
@@ -69,56 +74,244 @@ fn get_valid_neighbors(grid: &[Vec<u32>], coord: &Coordinate) -> Vec<Coordinate>
     vec
 }
 
+#[derive(Debug, Clone, Copy)]
+struct Rules {
+    // An octopus flashes once its energy goes above this
+    flash_threshold: u32,
+    // Energy of every octopus that flashed, at the end of the step
+    reset_value: u32,
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            flash_threshold: 9,
+            reset_value: 0,
+        }
+    }
+}
+
 fn increment_coordinate(grid: &mut [Vec<u32>], coord: &Coordinate) {
     grid[coord.row][coord.column] += 1;
 }
 
-fn reset_coordinate(grid: &mut [Vec<u32>], coord: &Coordinate) {
-    grid[coord.row][coord.column] = 0;
+fn reset_coordinate(grid: &mut [Vec<u32>], coord: &Coordinate, value: u32) {
+    grid[coord.row][coord.column] = value;
 }
 
 fn get_grid_value(grid: &[Vec<u32>], coord: &Coordinate) -> u32 {
     grid[coord.row][coord.column]
 }
 
-fn run_step(grid: &mut [Vec<u32>]) -> usize {
+struct FlashEvent {
+    cell: Coordinate,
+    // Neighbor whose flash pushed this cell over the threshold, None for wave 0
+    trigger: Option<Coordinate>,
+}
+
+// Cells that flashed in one step, grouped by cascade wave
+struct StepTrace {
+    step: usize,
+    waves: Vec<Vec<FlashEvent>>,
+}
+
+fn run_step(grid: &mut [Vec<u32>], rules: &Rules, mut trace: Option<&mut StepTrace>) -> usize {
     let mut flashed: HashSet<Coordinate> = HashSet::new();
-    let mut deq: VecDeque<Coordinate> = VecDeque::new();
+    let mut deq: VecDeque<(Coordinate, usize)> = VecDeque::new();
 
     for (row_ind, row) in grid.iter_mut().enumerate() {
         for (col_ind, val) in row.iter_mut().enumerate() {
             *val += 1;
-            if *val > 9 {
+            if *val > rules.flash_threshold {
                 let current = Coordinate {
                     row: row_ind,
                     column: col_ind,
                 };
-                deq.push_back(current);
+                deq.push_back((current, 0));
                 flashed.insert(current);
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.record(0, current, None);
+                }
             }
         }
     }
 
-    while let Some(coord) = deq.pop_front() {
+    while let Some((coord, wave)) = deq.pop_front() {
         let neighbors = get_valid_neighbors(grid, &coord);
         for neighbor in neighbors {
             increment_coordinate(grid, &neighbor);
-            if !flashed.contains(&neighbor) && get_grid_value(grid, &neighbor) > 9 {
+            if !flashed.contains(&neighbor)
+                && get_grid_value(grid, &neighbor) > rules.flash_threshold
+            {
                 flashed.insert(neighbor);
-                deq.push_back(neighbor);
+                deq.push_back((neighbor, wave + 1));
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.record(wave + 1, neighbor, Some(coord));
+                }
             }
         }
     }
 
     for coord in &flashed {
-        reset_coordinate(grid, coord);
+        reset_coordinate(grid, coord, rules.reset_value);
     }
 
     flashed.len()
 }
 
+impl StepTrace {
+    fn new(step: usize) -> StepTrace {
+        StepTrace {
+            step,
+            waves: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, wave: usize, cell: Coordinate, trigger: Option<Coordinate>) {
+        if self.waves.len() <= wave {
+            self.waves.resize_with(wave + 1, Vec::new);
+        }
+        self.waves[wave].push(FlashEvent { cell, trigger });
+    }
+}
+
+fn coordinate_json(coord: &Coordinate) -> String {
+    format!("[{},{}]", coord.row, coord.column)
+}
+
+// Writes `[{"step":1,"waves":[[{"cell":[row,column],"trigger":null},...],...]},...]`
+fn write_trace_json(out: &mut impl Write, traces: &[StepTrace]) -> io::Result<()> {
+    writeln!(out, "[")?;
+    for (index, trace) in traces.iter().enumerate() {
+        let waves: Vec<String> = trace
+            .waves
+            .iter()
+            .map(|wave| {
+                let events: Vec<String> = wave
+                    .iter()
+                    .map(|event| {
+                        let trigger = event
+                            .trigger
+                            .as_ref()
+                            .map_or(String::from("null"), coordinate_json);
+                        format!(
+                            "{{\"cell\":{},\"trigger\":{}}}",
+                            coordinate_json(&event.cell),
+                            trigger
+                        )
+                    })
+                    .collect();
+                format!("[{}]", events.join(","))
+            })
+            .collect();
+        let separator = if index + 1 < traces.len() { "," } else { "" };
+        writeln!(
+            out,
+            "  {{\"step\":{},\"waves\":[{}]}}{}",
+            trace.step,
+            waves.join(","),
+            separator
+        )?;
+    }
+    writeln!(out, "]")
+}
+
+struct Cycle {
+    // Steps before the grid first enters the repeating loop
+    pre_period: usize,
+    period: usize,
+    flashes_per_cycle: usize,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+struct State {
+    grid: Vec<Vec<u32>>,
+    hash: u64,
+}
+
+impl State {
+    fn new(grid: Vec<Vec<u32>>) -> State {
+        let mut hasher = DefaultHasher::new();
+        grid.hash(&mut hasher);
+        State {
+            hash: hasher.finish(),
+            grid,
+        }
+    }
+
+    fn step(&self, rules: &Rules) -> (State, usize) {
+        let mut grid = self.grid.clone();
+        let flashes = run_step(&mut grid, rules, None);
+        (State::new(grid), flashes)
+    }
+
+    fn same_as(&self, other: &State) -> bool {
+        self.hash == other.hash && self.grid == other.grid
+    }
+}
+
+// Brent's cycle detection over the grid states, giving up after `max_steps` steps
+fn find_cycle(grid: &[Vec<u32>], rules: &Rules, max_steps: usize) -> Option<Cycle> {
+    let start = State::new(grid.to_vec());
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = start.clone();
+    let mut hare = start.step(rules).0;
+    let mut steps = 1;
+    while !tortoise.same_as(&hare) {
+        if power == period {
+            tortoise = hare.clone();
+            power *= 2;
+            period = 0;
+        }
+        hare = hare.step(rules).0;
+        period += 1;
+        steps += 1;
+        if steps > max_steps {
+            return None;
+        }
+    }
+
+    let mut tortoise = start.clone();
+    let mut hare = start;
+    for _ in 0..period {
+        hare = hare.step(rules).0;
+    }
+    let mut pre_period = 0;
+    while !tortoise.same_as(&hare) {
+        tortoise = tortoise.step(rules).0;
+        hare = hare.step(rules).0;
+        pre_period += 1;
+    }
+
+    let mut flashes_per_cycle = 0;
+    for _ in 0..period {
+        let (next, flashes) = tortoise.step(rules);
+        flashes_per_cycle += flashes;
+        tortoise = next;
+    }
+
+    Some(Cycle {
+        pre_period,
+        period,
+        flashes_per_cycle,
+    })
+}
+
 fn main() {
-    let contents = include_str!("day11.txt");
+    // `--input <file>` reads a grid of any size. `--threshold n` and `--reset n` change the
+    // flash rules. `--max-steps n` (default 1000000) bounds the search for part 2, and
+    // `--cycle` looks for a repeating state within that many steps. `--trace <file>`
+    // writes every step's flash cascade as JSON.
+    let args: Vec<String> = env::args().collect();
+    let input = args.iter().position(|arg| arg == "--input").map(|index| {
+        let path = args.get(index + 1).expect("--input needs a file");
+        fs::read_to_string(path).expect("failed to read input")
+    });
+    let contents = match &input {
+        Some(input) => input.trim_end(),
+        None => include_str!("day11.txt"),
+    };
 
     let mut grid = contents
         .split('\n')
@@ -129,20 +322,227 @@ fn main() {
         })
         .collect::<Vec<_>>();
 
+    let flag = |name: &str| {
+        args.iter().position(|arg| arg == name).map(|index| {
+            args.get(index + 1)
+                .and_then(|value| value.parse::<usize>().ok())
+                .expect("expected a number")
+        })
+    };
+    let defaults = Rules::default();
+    let rules = Rules {
+        flash_threshold: flag("--threshold").map_or(defaults.flash_threshold, |n| n as u32),
+        reset_value: flag("--reset").map_or(defaults.reset_value, |n| n as u32),
+    };
+
+    let max_steps = flag("--max-steps").unwrap_or(1_000_000);
+    let cycle = args
+        .iter()
+        .any(|arg| arg == "--cycle")
+        .then(|| find_cycle(&grid, &rules, max_steps));
+    // Once the states repeat without every octopus flashing together, they never will
+    let step_limit = match &cycle {
+        Some(Some(cycle)) => max_steps.min(cycle.pre_period + cycle.period),
+        _ => max_steps,
+    };
+
+    let octopuses: usize = grid.iter().map(|row| row.len()).sum();
     let mut total_flashes: usize = 0;
+    let mut part_1 = 0;
+    let mut part_2 = None;
+    let trace_path = args
+        .iter()
+        .position(|arg| arg == "--trace")
+        .map(|index| args.get(index + 1).expect("--trace needs a file"));
+    let mut traces: Vec<StepTrace> = Vec::new();
     let mut counter = 0;
-    loop {
+    while counter < 100 || (part_2.is_none() && counter < step_limit) {
         counter += 1;
-        let flashes = run_step(&mut grid[..][..]);
+        let mut trace = StepTrace::new(counter);
+        let flashes = run_step(&mut grid[..][..], &rules, trace_path.map(|_| &mut trace));
+        if trace_path.is_some() {
+            traces.push(trace);
+        }
         total_flashes += flashes;
 
         if counter == 100 {
-            println!("Part 1: {:?}", total_flashes);
+            part_1 = total_flashes;
+        }
+
+        if flashes == octopuses && part_2.is_none() {
+            part_2 = Some(counter);
         }
+    }
+
+    println!("Part 1: {:?}", part_1);
+    match part_2 {
+        Some(step) => println!("Part 2: {:?}", step),
+        None if step_limit < max_steps => println!("Part 2: never synchronizes"),
+        None => println!(
+            "Part 2: unknown, not synchronized within {} steps",
+            max_steps
+        ),
+    }
+
+    match cycle {
+        Some(Some(cycle)) => println!(
+            "Cycle: pre-period {}, period {}, {} flashes per cycle",
+            cycle.pre_period, cycle.period, cycle.flashes_per_cycle
+        ),
+        Some(None) => println!("Cycle: none within {} steps", max_steps),
+        None => (),
+    }
+
+    if let Some(path) = trace_path {
+        let mut out = io::BufWriter::new(fs::File::create(path).expect("failed to create trace"));
+        write_trace_json(&mut out, &traces)
+            .and_then(|_| out.flush())
+            .expect("failed to write trace");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if flashes == 100 {
-            println!("Part 2: {:?}", counter);
-            break;
+    fn parse(contents: &str) -> Vec<Vec<u32>> {
+        contents
+            .lines()
+            .map(|row| row.chars().map(|ele| ele.to_digit(10).unwrap()).collect())
+            .collect()
+    }
+
+    // First step on which every cell flashes, as part 2 counts it
+    fn synchronized_step(grid: &[Vec<u32>], rules: &Rules, max_steps: usize) -> Option<usize> {
+        let mut grid = grid.to_vec();
+        let octopuses: usize = grid.iter().map(|row| row.len()).sum();
+        (1..=max_steps).find(|_| run_step(&mut grid, rules, None) == octopuses)
+    }
+
+    #[test]
+    fn steps_grids_of_any_size() {
+        let mut grid = parse("11111\n19991\n19191\n19991\n11111");
+        let rules = Rules::default();
+        assert_eq!(run_step(&mut grid, &rules, None), 9);
+        assert_eq!(grid, parse("34543\n40004\n50005\n40004\n34543"));
+        assert_eq!(run_step(&mut grid, &rules, None), 0);
+        assert_eq!(grid, parse("45654\n51115\n61116\n51115\n45654"));
+
+        assert_eq!(synchronized_step(&parse("99\n99\n99"), &rules, 10), Some(1));
+        assert_eq!(synchronized_step(&parse("05"), &rules, 1000), None);
+    }
+
+    #[test]
+    fn threshold_and_reset_change_the_rules() {
+        let rules = Rules {
+            flash_threshold: 5,
+            reset_value: 1,
+        };
+        let start = parse("56\n00");
+        let mut grid = start.clone();
+        assert_eq!(run_step(&mut grid, &rules, None), 2);
+        assert_eq!(grid, parse("11\n33"));
+        assert_eq!(run_step(&mut grid, &rules, None), 0);
+        assert_eq!(run_step(&mut grid, &rules, None), 0);
+        // The bottom row flashes and pushes the top row over the threshold
+        assert_eq!(run_step(&mut grid, &rules, None), 4);
+        assert_eq!(grid, parse("11\n11"));
+        assert_eq!(synchronized_step(&start, &rules, 10), Some(4));
+    }
+
+    #[test]
+    fn synchronizes_by_cell_count() {
+        let example = parse(
+            "5483143223\n2745854711\n5264556173\n6141336146\n6357385478\n\
+             4167524645\n2176841721\n6882881134\n4846848554\n5283751526",
+        );
+        let rules = Rules::default();
+        assert_eq!(synchronized_step(&example, &rules, 1000), Some(195));
+        let bundled = parse(include_str!("day11.txt"));
+        assert_eq!(synchronized_step(&bundled, &rules, 1000), Some(371));
+
+        // Part 2 on a 3x4 slice of the example counts 12 cells, not 100
+        let slice: Vec<Vec<u32>> = example[..3].iter().map(|row| row[..4].to_vec()).collect();
+        let mut grid = slice.clone();
+        let step = synchronized_step(&slice, &rules, 1000).unwrap();
+        for _ in 1..step {
+            assert!(run_step(&mut grid, &rules, None) < 12);
         }
+        assert_eq!(run_step(&mut grid, &rules, None), 12);
+    }
+
+    #[test]
+    fn finds_cycles() {
+        let rules = Rules::default();
+        let cycle = find_cycle(&parse(include_str!("day11.txt")), &rules, 10_000).unwrap();
+        // Once synchronized every cell flashes together every 10 steps
+        assert_eq!(cycle.pre_period, 371);
+        assert_eq!(cycle.period, 10);
+        assert_eq!(cycle.flashes_per_cycle, 100);
+
+        // 0,5 -> 6,0 after 5 steps (the 5 flashes) -> 0,5 after 4 more (the 6 flashes)
+        let grid = parse("05");
+        let cycle = find_cycle(&grid, &rules, 1000).unwrap();
+        assert_eq!(cycle.pre_period, 0);
+        assert_eq!(cycle.period, 9);
+        assert_eq!(cycle.flashes_per_cycle, 2);
+        assert_eq!(synchronized_step(&grid, &rules, cycle.period), None);
+
+        assert!(find_cycle(&grid, &rules, 5).is_none());
+    }
+
+    #[test]
+    fn traces_cascade_waves() {
+        let at = |row, column| Coordinate { row, column };
+        let mut grid = parse("8889");
+        let mut trace = StepTrace::new(1);
+        assert_eq!(run_step(&mut grid, &Rules::default(), Some(&mut trace)), 4);
+
+        // The 9 flashes first and each flash pushes its left neighbor over the threshold
+        let waves: Vec<Vec<(Coordinate, Option<Coordinate>)>> = trace
+            .waves
+            .iter()
+            .map(|wave| {
+                wave.iter()
+                    .map(|event| (event.cell, event.trigger))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            waves,
+            vec![
+                vec![(at(0, 3), None)],
+                vec![(at(0, 2), Some(at(0, 3)))],
+                vec![(at(0, 1), Some(at(0, 2)))],
+                vec![(at(0, 0), Some(at(0, 1)))],
+            ]
+        );
+
+        let mut out = Vec::new();
+        write_trace_json(&mut out, &[trace]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[\n  {\"step\":1,\"waves\":[[{\"cell\":[0,3],\"trigger\":null}],\
+             [{\"cell\":[0,2],\"trigger\":[0,3]}],[{\"cell\":[0,1],\"trigger\":[0,2]}],\
+             [{\"cell\":[0,0],\"trigger\":[0,1]}]]}\n]\n"
+        );
+    }
+
+    #[test]
+    fn simultaneous_flashes_share_a_wave() {
+        let at = |row, column| Coordinate { row, column };
+        let mut grid = parse("898");
+        let mut trace = StepTrace::new(7);
+        assert_eq!(run_step(&mut grid, &Rules::default(), Some(&mut trace)), 3);
+        assert_eq!(trace.step, 7);
+        assert_eq!(trace.waves.len(), 2);
+        let wave_1: Vec<(Coordinate, Option<Coordinate>)> = trace.waves[1]
+            .iter()
+            .map(|event| (event.cell, event.trigger))
+            .collect();
+        assert_eq!(
+            wave_1,
+            vec![(at(0, 0), Some(at(0, 1))), (at(0, 2), Some(at(0, 1)))]
+        );
     }
 }
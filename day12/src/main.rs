@@ -1,91 +1,877 @@
-use std::collections::{HashMap, VecDeque};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    env, fs,
+    io::{self, Write},
+};
 
 fn is_large_cave(cave: &str) -> bool {
     cave.chars().all(|c| c.is_ascii_uppercase())
 }
 
+// Splits `a-b` or the weighted `a-b:5` into its caves and weight, which defaults to 1
+fn parse_edge(edge: &str) -> (&str, &str, u64) {
+    let (caves, weight) = match edge.split_once(':') {
+        Some((caves, weight)) => (caves, weight.parse().expect("invalid edge weight")),
+        None => (edge, 1),
+    };
+    let mut sides = caves.split('-');
+    let first = sides.next().expect("no first node");
+    let second = sides.next().expect("no second node");
+    (first, second, weight)
+}
+
 fn convert_edges_to_map(edges: Vec<&str>) -> HashMap<&str, Vec<&str>> {
     let mut map: HashMap<&str, Vec<&str>> = HashMap::new();
     for edge in edges {
-        let mut sides = edge.split('-');
-        let first = sides.next().expect("no first node");
-        let second = sides.next().expect("no second node");
+        let (first, second, _) = parse_edge(edge);
 
-        map.entry(first).or_insert_with(Vec::new).push(second);
-        map.entry(second).or_insert_with(Vec::new).push(first);
+        map.entry(first).or_default().push(second);
+        map.entry(second).or_default().push(first);
     }
     map
 }
 
-fn traverse_map_p1<'a, 'b>(map: &'b HashMap<&'a str, Vec<&'a str>>) -> i32 {
-    let mut deq: VecDeque<(&str, Vec<&str>)> = VecDeque::from([("start", vec!["start"])]);
-    let mut paths: i32 = 0;
-
-    while let Some((id, path)) = deq.pop_front() {
-        let neighbors = map.get(id).expect("node does not exist in map");
-        for &neighbor in neighbors {
-            if is_large_cave(neighbor) || !path.contains(&neighbor) {
-                if neighbor == "end" {
-                    paths += 1
-                } else {
-                    let mut new_path = path.clone();
-                    new_path.push(neighbor);
-                    deq.push_back((neighbor, new_path))
+fn convert_edges_to_weights<'a>(edges: &[&'a str]) -> HashMap<(&'a str, &'a str), u64> {
+    let mut weights = HashMap::new();
+    for edge in edges {
+        let (first, second, weight) = parse_edge(edge);
+        weights.insert((first, second), weight);
+        weights.insert((second, first), weight);
+    }
+    weights
+}
+
+// Caves interned to indices so small-cave visits fit in a bitmask
+struct CaveGraph<'a> {
+    names: Vec<&'a str>,
+    ids: HashMap<&'a str, usize>,
+    large: Vec<bool>,
+    neighbors: Vec<Vec<usize>>,
+    // Weight of the edge to each entry of `neighbors`
+    weights: Vec<Vec<u64>>,
+    start: usize,
+    end: usize,
+}
+
+impl<'a> CaveGraph<'a> {
+    fn from_map(
+        map: &HashMap<&'a str, Vec<&'a str>>,
+        weights: &HashMap<(&'a str, &'a str), u64>,
+    ) -> CaveGraph<'a> {
+        let mut names: Vec<&str> = map.keys().copied().collect();
+        names.sort_unstable();
+        assert!(names.len() <= 64, "too many caves for a u64 visit mask");
+        let ids: HashMap<&str, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(id, &name)| (name, id))
+            .collect();
+
+        CaveGraph {
+            large: names.iter().map(|name| is_large_cave(name)).collect(),
+            neighbors: names
+                .iter()
+                .map(|name| map[name].iter().map(|neighbor| ids[neighbor]).collect())
+                .collect(),
+            weights: names
+                .iter()
+                .map(|&name| {
+                    map[name]
+                        .iter()
+                        .map(|&neighbor| weights[&(name, neighbor)])
+                        .collect()
+                })
+                .collect(),
+            start: *ids.get("start").expect("no start cave"),
+            end: *ids.get("end").expect("no end cave"),
+            ids,
+            names,
+        }
+    }
+}
+
+// Which caves a path may enter and how often. Large caves are unlimited and small caves
+// are visited once, except that up to `repeated_caves` small caves may each be visited up to
+// `max_visits` times. `limits` overrides this for specific caves, where 0 forbids a cave.
+// `start` is never re-entered.
+struct RevisitPolicy<'a> {
+    repeated_caves: usize,
+    max_visits: usize,
+    limits: HashMap<&'a str, usize>,
+}
+
+impl<'a> RevisitPolicy<'a> {
+    fn new(repeated_caves: usize, max_visits: usize) -> RevisitPolicy<'a> {
+        RevisitPolicy {
+            repeated_caves,
+            max_visits,
+            limits: HashMap::new(),
+        }
+    }
+
+    // Part 1: every small cave at most once
+    fn single_visit() -> RevisitPolicy<'a> {
+        RevisitPolicy::new(0, 1)
+    }
+
+    // Part 2: one small cave may be visited twice
+    fn one_revisit() -> RevisitPolicy<'a> {
+        RevisitPolicy::new(1, 2)
+    }
+
+    fn limit(mut self, cave: &'a str, visits: usize) -> RevisitPolicy<'a> {
+        self.limits.insert(cave, visits);
+        self
+    }
+
+    fn forbid(self, cave: &'a str) -> RevisitPolicy<'a> {
+        self.limit(cave, 0)
+    }
+}
+
+// Visit counts along the current path: caves entered at least once are in `visited`, and
+// caves entered more than once are listed in `repeats` with their count, sorted by id
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Visits {
+    visited: u64,
+    repeats: Vec<(usize, usize)>,
+}
+
+impl Visits {
+    fn count(&self, cave: usize) -> usize {
+        if self.visited & 1 << cave == 0 {
+            return 0;
+        }
+        match self.repeats.binary_search_by_key(&cave, |&(id, _)| id) {
+            Ok(index) => self.repeats[index].1,
+            Err(_) => 1,
+        }
+    }
+
+    fn enter(&self, cave: usize) -> Visits {
+        let mut next = self.clone();
+        if next.visited & 1 << cave == 0 {
+            next.visited |= 1 << cave;
+        } else {
+            match next.repeats.binary_search_by_key(&cave, |&(id, _)| id) {
+                Ok(index) => next.repeats[index].1 += 1,
+                Err(index) => next.repeats.insert(index, (cave, 2)),
+            }
+        }
+        next
+    }
+}
+
+// A revisit policy resolved against the cave ids of one graph
+struct VisitRules<'g> {
+    graph: &'g CaveGraph<'g>,
+    // Explicit visit limit per cave id from the policy; None for the default rules
+    limits: Vec<Option<usize>>,
+    repeated_caves: usize,
+    max_visits: usize,
+}
+
+impl<'g> VisitRules<'g> {
+    fn new(graph: &'g CaveGraph<'g>, policy: &RevisitPolicy) -> VisitRules<'g> {
+        let mut limits = vec![None; graph.large.len()];
+        for (cave, &limit) in &policy.limits {
+            if let Some(&id) = graph.ids.get(cave) {
+                limits[id] = Some(limit);
+            }
+        }
+        VisitRules {
+            graph,
+            limits,
+            repeated_caves: policy.repeated_caves,
+            max_visits: policy.max_visits,
+        }
+    }
+
+    fn start(&self) -> Visits {
+        Visits {
+            visited: 1 << self.graph.start,
+            repeats: Vec::new(),
+        }
+    }
+
+    fn can_enter(&self, cave: usize, visits: &Visits) -> bool {
+        if cave == self.graph.start {
+            return false;
+        }
+        let count = visits.count(cave);
+        match self.limits[cave] {
+            Some(limit) => count < limit,
+            None if self.graph.large[cave] => true,
+            None if count == 0 => true,
+            None => {
+                let repeated = visits
+                    .repeats
+                    .iter()
+                    .filter(|&&(id, _)| self.limits[id].is_none())
+                    .count();
+                count < self.max_visits && (count > 1 || repeated < self.repeated_caves)
+            }
+        }
+    }
+
+    // Whether visits to this cave need tracking at all
+    fn tracked(&self, cave: usize) -> bool {
+        !self.graph.large[cave] || self.limits[cave].is_some()
+    }
+
+    // The visits after stepping into `cave`
+    fn enter(&self, cave: usize, visits: &Visits) -> Visits {
+        if self.tracked(cave) {
+            visits.enter(cave)
+        } else {
+            visits.clone()
+        }
+    }
+}
+
+type PathMemo = HashMap<(usize, Visits), u64>;
+
+struct PathCounter<'g> {
+    rules: VisitRules<'g>,
+    memo: PathMemo,
+}
+
+impl PathCounter<'_> {
+    // Paths from `cave` to the end given the visits made so far
+    fn count_from(&mut self, cave: usize, visits: Visits) -> u64 {
+        if cave == self.rules.graph.end {
+            return 1;
+        }
+        let key = (cave, visits);
+        if let Some(&paths) = self.memo.get(&key) {
+            return paths;
+        }
+        let visits = &key.1;
+
+        let mut paths = 0;
+        for &neighbor in &self.rules.graph.neighbors[cave] {
+            if !self.rules.can_enter(neighbor, visits) {
+                continue;
+            }
+            let next = self.rules.enter(neighbor, visits);
+            paths += self.count_from(neighbor, next);
+        }
+
+        self.memo.insert(key, paths);
+        paths
+    }
+}
+
+fn count_paths(graph: &CaveGraph, policy: &RevisitPolicy) -> u64 {
+    let mut counter = PathCounter {
+        rules: VisitRules::new(graph, policy),
+        memo: HashMap::new(),
+    };
+    let start = counter.rules.start();
+    counter.count_from(graph.start, start)
+}
+
+// Depth-first enumeration of the paths from start to end allowed by a policy, yielding the
+// caves along each path. `max_length` prunes paths longer than that many edges, and
+// `through` keeps only paths that visit every given cave.
+struct Paths<'g> {
+    rules: VisitRules<'g>,
+    // The current path as (cave, next neighbor to try, visits after entering the cave)
+    stack: Vec<(usize, usize, Visits)>,
+    max_length: usize,
+    through: Vec<usize>,
+}
+
+impl<'g> Paths<'g> {
+    fn new(graph: &'g CaveGraph<'g>, policy: &RevisitPolicy) -> Paths<'g> {
+        let rules = VisitRules::new(graph, policy);
+        let stack = vec![(graph.start, 0, rules.start())];
+        Paths {
+            rules,
+            stack,
+            max_length: usize::MAX,
+            through: Vec::new(),
+        }
+    }
+
+    fn max_length(mut self, edges: usize) -> Paths<'g> {
+        self.max_length = edges;
+        self
+    }
+
+    fn through(mut self, cave: &str) -> Paths<'g> {
+        match self.rules.graph.ids.get(cave) {
+            Some(&id) => self.through.push(id),
+            // No path can visit a cave that isn't in the graph
+            None => self.stack.clear(),
+        }
+        self
+    }
+}
+
+impl<'g> Iterator for Paths<'g> {
+    type Item = Vec<&'g str>;
+
+    fn next(&mut self) -> Option<Vec<&'g str>> {
+        let graph = self.rules.graph;
+        loop {
+            let depth = self.stack.len();
+            let (cave, index, visits) = self.stack.last_mut()?;
+            let Some(&neighbor) = graph.neighbors[*cave].get(*index) else {
+                self.stack.pop();
+                continue;
+            };
+            *index += 1;
+            if depth > self.max_length || !self.rules.can_enter(neighbor, visits) {
+                continue;
+            }
+
+            if neighbor == graph.end {
+                let mut path: Vec<usize> = self.stack.iter().map(|&(cave, _, _)| cave).collect();
+                path.push(neighbor);
+                if self.through.iter().all(|cave| path.contains(cave)) {
+                    return Some(path.iter().map(|&cave| graph.names[cave]).collect());
                 }
+                continue;
             }
+            let next = self.rules.enter(neighbor, visits);
+            self.stack.push((neighbor, 0, next));
         }
     }
+}
+
+// Graphviz rendering of the cave map: large caves as filled boxes, small caves as
+// ellipses and the start and end as double circles
+fn write_dot(out: &mut impl Write, map: &HashMap<&str, Vec<&str>>) -> io::Result<()> {
+    let mut caves: Vec<&str> = map.keys().copied().collect();
+    caves.sort_unstable();
 
-    paths
+    writeln!(out, "graph caves {{")?;
+    for cave in &caves {
+        let style = match *cave {
+            "start" | "end" => "shape=doublecircle",
+            _ if is_large_cave(cave) => "shape=box, style=filled, fillcolor=lightgray",
+            _ => "shape=ellipse",
+        };
+        writeln!(out, "    \"{}\" [{}];", cave, style)?;
+    }
+    for cave in &caves {
+        let mut neighbors = map[cave].clone();
+        neighbors.sort_unstable();
+        for neighbor in neighbors.into_iter().filter(|neighbor| cave < neighbor) {
+            writeln!(out, "    \"{}\" -- \"{}\";", cave, neighbor)?;
+        }
+    }
+    writeln!(out, "}}")
 }
 
-fn traverse_map_p2<'a, 'b>(map: &'b HashMap<&'a str, Vec<&'a str>>) -> i32 {
-    let mut deq: VecDeque<(&str, Vec<&str>, bool)> =
-        VecDeque::from([("start", vec!["start"], false)]);
-    let mut paths: i32 = 0;
+// Dijkstra from start to end over the caves the policy lets a path enter. With
+// non-negative weights the shortest walk never repeats a cave, so only forbidden caves
+// (and `start`) matter here.
+fn shortest_path<'a>(graph: &CaveGraph<'a>, policy: &RevisitPolicy) -> Option<(u64, Vec<&'a str>)> {
+    let rules = VisitRules::new(graph, policy);
+    let open = rules.start();
+    let mut distance = vec![u64::MAX; graph.names.len()];
+    let mut previous = vec![usize::MAX; graph.names.len()];
+    let mut queue = BinaryHeap::new();
+    distance[graph.start] = 0;
+    queue.push(Reverse((0, graph.start)));
 
-    while let Some((id, path, dupe)) = deq.pop_front() {
-        let neighbors = map.get(id).expect("node does not exist in map");
-        for &neighbor in neighbors {
-            if dupe {
-                if is_large_cave(neighbor) || !path.contains(&neighbor) {
-                    if neighbor == "end" {
-                        paths += 1;
-                    } else {
-                        let mut new_path = path.clone();
-                        new_path.push(neighbor);
-                        deq.push_back((neighbor, new_path, dupe))
-                    }
+    while let Some(Reverse((length, cave))) = queue.pop() {
+        if cave == graph.end {
+            let mut path = vec![graph.names[cave]];
+            let mut current = cave;
+            while current != graph.start {
+                current = previous[current];
+                path.push(graph.names[current]);
+            }
+            path.reverse();
+            return Some((length, path));
+        }
+        if length > distance[cave] {
+            continue;
+        }
+        for (&neighbor, &weight) in graph.neighbors[cave].iter().zip(&graph.weights[cave]) {
+            if !rules.can_enter(neighbor, &open) || length + weight >= distance[neighbor] {
+                continue;
+            }
+            distance[neighbor] = length + weight;
+            previous[neighbor] = cave;
+            queue.push(Reverse((length + weight, neighbor)));
+        }
+    }
+    None
+}
+
+// Branch and bound search for the heaviest path allowed by a policy
+struct LongestSearch<'g> {
+    rules: VisitRules<'g>,
+    path: Vec<usize>,
+    best: Option<(u64, Vec<usize>)>,
+    max_weight: u64,
+}
+
+impl LongestSearch<'_> {
+    // Upper bound on the number of caves with tracked visits that can still be entered
+    fn entries_left(&self, visits: &Visits) -> usize {
+        let graph = self.rules.graph;
+        let mut entries = 0;
+        let mut repeated = 0;
+        for cave in 0..graph.names.len() {
+            if cave == graph.start || cave == graph.end || !self.rules.tracked(cave) {
+                continue;
+            }
+            let count = visits.count(cave);
+            entries += match self.rules.limits[cave] {
+                Some(limit) => limit.saturating_sub(count),
+                None if count > 1 => {
+                    repeated += 1;
+                    self.rules.max_visits.saturating_sub(count)
                 }
-            } else if neighbor != "start" {
-                if neighbor == "end" {
-                    paths += 1;
-                } else {
-                    let mut new_path = path.clone();
-                    new_path.push(neighbor);
-                    let dupe = path
-                        .iter()
-                        .copied()
-                        .filter(|&cave| !is_large_cave(cave))
-                        .any(|cave| cave == neighbor);
-                    deq.push_back((neighbor, new_path, dupe));
+                None => 1usize.saturating_sub(count),
+            };
+        }
+        let budget = self.rules.repeated_caves.saturating_sub(repeated);
+        entries + budget * self.rules.max_visits.saturating_sub(1)
+    }
+
+    // Every edge touches a tracked cave or the end, so reaching each remaining entry (and
+    // finally the end) takes at most two edges
+    fn bound(&self, length: u64, visits: &Visits) -> u64 {
+        length + (self.entries_left(visits) as u64 + 1) * 2 * self.max_weight
+    }
+
+    fn search(&mut self, cave: usize, visits: &Visits, length: u64) {
+        let graph = self.rules.graph;
+        for (&neighbor, &weight) in graph.neighbors[cave].iter().zip(&graph.weights[cave]) {
+            if !self.rules.can_enter(neighbor, visits) {
+                continue;
+            }
+            let length = length + weight;
+            if neighbor == graph.end {
+                if self.best.as_ref().is_none_or(|(best, _)| length > *best) {
+                    let mut path = self.path.clone();
+                    path.push(neighbor);
+                    self.best = Some((length, path));
                 }
+                continue;
+            }
+
+            let next = self.rules.enter(neighbor, visits);
+            if self
+                .best
+                .as_ref()
+                .is_some_and(|(best, _)| self.bound(length, &next) <= *best)
+            {
+                continue;
             }
+            self.path.push(neighbor);
+            self.search(neighbor, &next, length);
+            self.path.pop();
         }
     }
+}
 
-    paths
+// Heaviest start-to-end path under a policy. Two adjacent caves without visit limits would
+// allow walks of any length, so that is an error.
+fn longest_path<'a>(
+    graph: &CaveGraph<'a>,
+    policy: &RevisitPolicy,
+) -> Result<Option<(u64, Vec<&'a str>)>, String> {
+    let rules = VisitRules::new(graph, policy);
+    for cave in (0..graph.names.len()).filter(|&cave| !rules.tracked(cave)) {
+        if let Some(&neighbor) = graph.neighbors[cave]
+            .iter()
+            .find(|&&neighbor| !rules.tracked(neighbor))
+        {
+            return Err(format!(
+                "caves {} and {} can be revisited without limit, so paths are unbounded",
+                graph.names[cave], graph.names[neighbor]
+            ));
+        }
+    }
+
+    let max_weight = graph.weights.iter().flatten().copied().max().unwrap_or(0);
+    let mut search = LongestSearch {
+        rules,
+        path: vec![graph.start],
+        best: None,
+        max_weight,
+    };
+    let start = search.rules.start();
+    search.search(graph.start, &start, 0);
+    Ok(search
+        .best
+        .map(|(length, path)| (length, path.iter().map(|&cave| graph.names[cave]).collect())))
 }
 
 fn main() {
-    let contents = include_str!("day12.txt");
+    // `--input <file>` reads another cave system, whose edges may carry weights (`a-b:5`).
+    // `--repeat k` lets up to k small caves be revisited and `--max-visits n` caps each of
+    // them at n visits (default 2). `--limit cave=n,...` sets per-cave limits and
+    // `--forbid cave,...` excludes caves entirely.
+    // `--paths` lists the paths under that policy (part 1 rules by default), optionally only
+    // those `--through cave,...` and at most `--max-length n` edges long.
+    // `--shortest` and `--longest` report the lightest and heaviest paths under the policy.
+    // `--dot <file>` writes the cave map as a Graphviz graph.
+    let args: Vec<String> = env::args().collect();
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .map(|index| args.get(index + 1).expect("missing flag value").as_str())
+    };
+
+    let input = flag("--input").map(|path| fs::read_to_string(path).expect("failed to read input"));
+    let contents = match &input {
+        Some(input) => input.trim_end(),
+        None => include_str!("day12.txt"),
+    };
     let edges: Vec<&str> = contents.split('\n').collect();
 
+    let weights = convert_edges_to_weights(&edges);
     let map = convert_edges_to_map(edges);
 
-    let paths_p1 = traverse_map_p1(&map);
+    let graph = CaveGraph::from_map(&map, &weights);
+
+    let paths_p1 = count_paths(&graph, &RevisitPolicy::single_visit());
     println!("Part 1: {:?}", paths_p1);
 
-    let paths_p2 = traverse_map_p2(&map);
+    let paths_p2 = count_paths(&graph, &RevisitPolicy::one_revisit());
     println!("Part 2: {:?}", paths_p2);
+
+    let custom = ["--repeat", "--max-visits", "--limit", "--forbid"]
+        .iter()
+        .any(|name| flag(name).is_some());
+    let mut policy = RevisitPolicy::single_visit();
+    if custom {
+        let repeated_caves =
+            flag("--repeat").map_or(0, |k| k.parse().expect("invalid repeat count"));
+        let max_visits =
+            flag("--max-visits").map_or(2, |n| n.parse().expect("invalid visit count"));
+        policy = RevisitPolicy::new(repeated_caves, max_visits);
+        for limit in flag("--limit")
+            .into_iter()
+            .flat_map(|limits| limits.split(','))
+        {
+            let (cave, visits) = limit.split_once('=').expect("expected cave=visits");
+            policy = policy.limit(cave, visits.parse().expect("invalid visit limit"));
+        }
+        for cave in flag("--forbid")
+            .into_iter()
+            .flat_map(|caves| caves.split(','))
+        {
+            policy = policy.forbid(cave);
+        }
+        println!("Policy: {:?}", count_paths(&graph, &policy));
+    }
+
+    if args.iter().any(|arg| arg == "--paths") {
+        let mut paths = Paths::new(&graph, &policy);
+        if let Some(length) = flag("--max-length") {
+            paths = paths.max_length(length.parse().expect("invalid path length"));
+        }
+        for cave in flag("--through")
+            .into_iter()
+            .flat_map(|caves| caves.split(','))
+        {
+            paths = paths.through(cave);
+        }
+        for path in paths {
+            println!("{}", path.join(","));
+        }
+    }
+
+    let report = |name: &str, path: Option<(u64, Vec<&str>)>| match path {
+        Some((length, path)) => println!("{}: {} {}", name, length, path.join(",")),
+        None => println!("{}: no path", name),
+    };
+    if args.iter().any(|arg| arg == "--shortest") {
+        report("Shortest", shortest_path(&graph, &policy));
+    }
+    if args.iter().any(|arg| arg == "--longest") {
+        match longest_path(&graph, &policy) {
+            Ok(path) => report("Longest", path),
+            Err(error) => println!("Longest: {}", error),
+        }
+    }
+
+    if let Some(path) = flag("--dot") {
+        let mut out =
+            io::BufWriter::new(fs::File::create(path).expect("failed to create dot file"));
+        write_dot(&mut out, &map).expect("failed to write dot file");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const SMALL: &str = "start-A\nstart-b\nA-c\nA-b\nb-d\nA-end\nb-end";
+    const MEDIUM: &str =
+        "dc-end\nHN-start\nstart-kj\ndc-start\ndc-HN\nLN-dc\nHN-end\nkj-sy\nkj-HN\n\
+                          kj-dc";
+    const LARGE: &str = "fs-end\nhe-DX\nfs-he\nstart-DX\npj-DX\nend-zg\nzg-sl\nzg-pj\npj-he\n\
+                         RW-he\nfs-DX\npj-RW\nzg-RW\nstart-pj\nhe-WI\nzg-he\npj-fs\nstart-RW";
+
+    // Builds the graph for `contents` and hands it to `check`, as the graph borrows the map
+    fn with_graph<T>(contents: &str, check: impl FnOnce(&CaveGraph) -> T) -> T {
+        let edges: Vec<&str> = contents.split('\n').collect();
+        let weights = convert_edges_to_weights(&edges);
+        let map = convert_edges_to_map(edges);
+        check(&CaveGraph::from_map(&map, &weights))
+    }
+
+    fn count(contents: &str, policy: &RevisitPolicy) -> u64 {
+        with_graph(contents, |graph| count_paths(graph, policy))
+    }
+
+    #[test]
+    fn counts_example_paths() {
+        for (contents, part_1, part_2) in [
+            (SMALL, 10, 36),
+            (MEDIUM, 19, 103),
+            (LARGE, 226, 3509),
+            (include_str!("day12.txt"), 3230, 83475),
+        ] {
+            assert_eq!(count(contents, &RevisitPolicy::single_visit()), part_1);
+            assert_eq!(count(contents, &RevisitPolicy::one_revisit()), part_2);
+        }
+    }
+
+    #[test]
+    fn policies_bound_visits() {
+        // Paths are start,A then excursions A,b,A in any number, then A,end
+        let one_small = "start-A\nA-b\nA-end";
+        assert_eq!(count(one_small, &RevisitPolicy::single_visit()), 2);
+        assert_eq!(count(one_small, &RevisitPolicy::one_revisit()), 3);
+        assert_eq!(count(one_small, &RevisitPolicy::new(1, 3)), 4);
+        assert_eq!(
+            count(one_small, &RevisitPolicy::single_visit().limit("b", 5)),
+            6
+        );
+        assert_eq!(
+            count(one_small, &RevisitPolicy::one_revisit().forbid("b")),
+            1
+        );
+        assert_eq!(
+            count(one_small, &RevisitPolicy::one_revisit().forbid("A")),
+            0
+        );
+        // A large cave with a limit is tracked like a small one
+        assert_eq!(
+            count(one_small, &RevisitPolicy::one_revisit().limit("A", 1)),
+            1
+        );
+        assert_eq!(
+            count(one_small, &RevisitPolicy::one_revisit().limit("A", 2)),
+            2
+        );
+
+        // With two small caves a path is an ordering of its b and c excursions, so visiting
+        // them (nb, nc) times gives (nb + nc)! / (nb! nc!) paths
+        let two_small = "start-A\nA-b\nA-c\nA-end";
+        // (0,0) (1,0) (0,1) (1,1)
+        assert_eq!(
+            count(two_small, &RevisitPolicy::single_visit()),
+            1 + 1 + 1 + 2
+        );
+        // ... and (2,0) (0,2) (2,1) (1,2)
+        assert_eq!(
+            count(two_small, &RevisitPolicy::new(1, 2)),
+            5 + 1 + 1 + 3 + 3
+        );
+        // ... and (2,2)
+        assert_eq!(count(two_small, &RevisitPolicy::new(2, 2)), 13 + 6);
+        // ... and (3,0) (0,3) (3,1) (1,3)
+        assert_eq!(
+            count(two_small, &RevisitPolicy::new(1, 3)),
+            13 + 1 + 1 + 4 + 4
+        );
+        // A per-cave limit does not use up the repeat budget: b up to twice, c up to twice
+        assert_eq!(
+            count(two_small, &RevisitPolicy::new(1, 2).limit("b", 2)),
+            19
+        );
+        // b at most once and c twice: (0,0) (1,0) (0,1) (1,1) (0,2) (1,2)
+        assert_eq!(
+            count(two_small, &RevisitPolicy::new(1, 2).limit("b", 1)),
+            5 + 1 + 3
+        );
+
+        assert_eq!(count(SMALL, &RevisitPolicy::new(1, 3)), 83);
+        assert_eq!(count(SMALL, &RevisitPolicy::single_visit().forbid("A")), 1);
+    }
+
+    #[test]
+    fn enumerates_every_counted_path() {
+        for contents in [SMALL, MEDIUM, LARGE] {
+            for policy in [RevisitPolicy::single_visit(), RevisitPolicy::one_revisit()] {
+                with_graph(contents, |graph| {
+                    let paths: Vec<Vec<&str>> = Paths::new(graph, &policy).collect();
+                    let unique: HashSet<&Vec<&str>> = paths.iter().collect();
+                    assert_eq!(unique.len(), paths.len());
+                    assert_eq!(paths.len() as u64, count_paths(graph, &policy));
+                    for path in &paths {
+                        assert_eq!(path.first(), Some(&"start"));
+                        assert_eq!(path.last(), Some(&"end"));
+                    }
+                });
+            }
+        }
+
+        with_graph(SMALL, |graph| {
+            let paths: HashSet<String> = Paths::new(graph, &RevisitPolicy::single_visit())
+                .map(|path| path.join(","))
+                .collect();
+            let expected: HashSet<String> = [
+                "start,A,b,A,c,A,end",
+                "start,A,b,A,end",
+                "start,A,b,end",
+                "start,A,c,A,b,A,end",
+                "start,A,c,A,b,end",
+                "start,A,c,A,end",
+                "start,A,end",
+                "start,b,A,c,A,end",
+                "start,b,A,end",
+                "start,b,end",
+            ]
+            .iter()
+            .map(|path| path.to_string())
+            .collect();
+            assert_eq!(paths, expected);
+        });
+    }
+
+    #[test]
+    fn filters_paths_by_length_and_cave() {
+        with_graph(SMALL, |graph| {
+            let policy = RevisitPolicy::single_visit();
+            let joined = |paths: Paths| {
+                let mut paths: Vec<String> = paths.map(|path| path.join(",")).collect();
+                paths.sort();
+                paths
+            };
+            assert_eq!(
+                joined(Paths::new(graph, &policy).max_length(2)),
+                ["start,A,end", "start,b,end"]
+            );
+            assert_eq!(
+                joined(Paths::new(graph, &policy).max_length(3)),
+                [
+                    "start,A,b,end",
+                    "start,A,end",
+                    "start,b,A,end",
+                    "start,b,end"
+                ]
+            );
+            assert_eq!(
+                joined(Paths::new(graph, &policy).through("c").through("b")),
+                [
+                    "start,A,b,A,c,A,end",
+                    "start,A,c,A,b,A,end",
+                    "start,A,c,A,b,end",
+                    "start,b,A,c,A,end"
+                ]
+            );
+            assert_eq!(
+                joined(Paths::new(graph, &policy).through("c").max_length(5)),
+                ["start,A,c,A,b,end", "start,A,c,A,end", "start,b,A,c,A,end"]
+            );
+            assert!(joined(Paths::new(graph, &policy).through("d")).is_empty());
+            assert!(joined(Paths::new(graph, &policy).through("missing")).is_empty());
+        });
+    }
+
+    #[test]
+    fn writes_dot() {
+        let map = convert_edges_to_map(vec!["start-A", "A-b", "A-end"]);
+        let mut out = Vec::new();
+        write_dot(&mut out, &map).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "graph caves {\n\
+             \x20   \"A\" [shape=box, style=filled, fillcolor=lightgray];\n\
+             \x20   \"b\" [shape=ellipse];\n\
+             \x20   \"end\" [shape=doublecircle];\n\
+             \x20   \"start\" [shape=doublecircle];\n\
+             \x20   \"A\" -- \"b\";\n\
+             \x20   \"A\" -- \"end\";\n\
+             \x20   \"A\" -- \"start\";\n\
+             }\n"
+        );
+    }
+
+    const WEIGHTED: &str = "start-A:2\nstart-b:7\nA-c:1\nA-b:3\nb-d:4\nA-end:10\nb-end:1";
+
+    #[test]
+    fn parses_weighted_edges() {
+        assert_eq!(parse_edge("A-b"), ("A", "b", 1));
+        assert_eq!(parse_edge("A-b:12"), ("A", "b", 12));
+        let weights = convert_edges_to_weights(&["start-A:2", "A-end"]);
+        assert_eq!(weights[&("A", "start")], 2);
+        assert_eq!(weights[&("start", "A")], 2);
+        assert_eq!(weights[&("end", "A")], 1);
+        // Weights do not change which paths exist
+        assert_eq!(count(WEIGHTED, &RevisitPolicy::single_visit()), 10);
+    }
+
+    #[test]
+    fn finds_shortest_paths() {
+        with_graph(WEIGHTED, |graph| {
+            assert_eq!(
+                shortest_path(graph, &RevisitPolicy::single_visit()),
+                Some((6, vec!["start", "A", "b", "end"]))
+            );
+            assert_eq!(
+                shortest_path(graph, &RevisitPolicy::single_visit().forbid("A")),
+                Some((8, vec!["start", "b", "end"]))
+            );
+            let policy = RevisitPolicy::single_visit().forbid("A").forbid("b");
+            assert_eq!(shortest_path(graph, &policy), None);
+        });
+        // Unweighted edges count steps
+        with_graph(SMALL, |graph| {
+            let (length, _) = shortest_path(graph, &RevisitPolicy::single_visit()).unwrap();
+            assert_eq!(length, 2);
+        });
+    }
+
+    #[test]
+    fn finds_longest_paths() {
+        with_graph(WEIGHTED, |graph| {
+            assert_eq!(
+                longest_path(graph, &RevisitPolicy::single_visit()),
+                Ok(Some((22, vec!["start", "b", "A", "c", "A", "end"])))
+            );
+            assert_eq!(
+                longest_path(graph, &RevisitPolicy::one_revisit()),
+                Ok(Some((
+                    30,
+                    vec!["start", "b", "d", "b", "A", "c", "A", "end"]
+                )))
+            );
+            assert_eq!(
+                longest_path(graph, &RevisitPolicy::single_visit().forbid("A")),
+                Ok(Some((8, vec!["start", "b", "end"])))
+            );
+            let policy = RevisitPolicy::single_visit().forbid("A").forbid("b");
+            assert_eq!(longest_path(graph, &policy), Ok(None));
+        });
+    }
+
+    #[test]
+    fn rejects_unbounded_longest_paths() {
+        let adjacent_large = "start-A\nA-B\nB-end";
+        with_graph(adjacent_large, |graph| {
+            assert!(longest_path(graph, &RevisitPolicy::single_visit()).is_err());
+            // A limit on either cave bounds the walk
+            let policy = RevisitPolicy::single_visit().limit("B", 2);
+            assert_eq!(
+                longest_path(graph, &policy),
+                Ok(Some((5, vec!["start", "A", "B", "A", "B", "end"])))
+            );
+        });
+    }
 }
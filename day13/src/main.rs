@@ -1,5 +1,7 @@
 use std::cmp;
 use std::collections::HashSet;
+use std::env;
+use std::fs;
 
 #[derive(Debug, PartialEq, Eq, Hash)]
 struct Point {
@@ -36,14 +38,168 @@ struct Fold {
     location: u32,
 }
 
-fn main() {
-    let contents = include_str!("day13.txt").trim_end();
+// Fixed-width dot-matrix letters with `spacing` blank columns between them. Each glyph
+// lists its rows separated by spaces.
+struct Font {
+    width: usize,
+    height: usize,
+    spacing: usize,
+    glyphs: &'static [(char, &'static str)],
+}
+
+// The 4x6 letters used by most puzzles
+const SMALL_FONT: Font = Font {
+    width: 4,
+    height: 6,
+    spacing: 1,
+    glyphs: &[
+        ('A', ".##. #..# #..# #### #..# #..#"),
+        ('B', "###. #..# ###. #..# #..# ###."),
+        ('C', ".##. #..# #... #... #..# .##."),
+        ('E', "#### #... ###. #... #... ####"),
+        ('F', "#### #... ###. #... #... #..."),
+        ('G', ".##. #..# #... #.## #..# .###"),
+        ('H', "#..# #..# #### #..# #..# #..#"),
+        ('I', ".### ..#. ..#. ..#. ..#. .###"),
+        ('J', "..## ...# ...# ...# #..# .##."),
+        ('K', "#..# #.#. ##.. #.#. #.#. #..#"),
+        ('L', "#... #... #... #... #... ####"),
+        ('O', ".##. #..# #..# #..# #..# .##."),
+        ('P', "###. #..# #..# ###. #... #..."),
+        ('R', "###. #..# #..# ###. #.#. #..#"),
+        ('S', ".### #... #... .##. ...# ###."),
+        ('U', "#..# #..# #..# #..# #..# .##."),
+        ('Z', "#### ...# ..#. .#.. #... ####"),
+    ],
+};
+
+// The taller 6x10 variant
+#[rustfmt::skip]
+const LARGE_FONT: Font = Font {
+    width: 6,
+    height: 10,
+    spacing: 2,
+    glyphs: &[
+        ('A', "..##.. .#..#. #....# #....# #....# ###### #....# #....# #....# #....#"),
+        ('B', "#####. #....# #....# #....# #####. #....# #....# #....# #....# #####."),
+        ('C', ".####. #....# #..... #..... #..... #..... #..... #..... #....# .####."),
+        ('E', "###### #..... #..... #..... #####. #..... #..... #..... #..... ######"),
+        ('F', "###### #..... #..... #..... #####. #..... #..... #..... #..... #....."),
+        ('G', ".####. #....# #..... #..... #..... #..### #....# #....# #...## .###.#"),
+        ('H', "#....# #....# #....# #....# ###### #....# #....# #....# #....# #....#"),
+        ('J', "...### ....#. ....#. ....#. ....#. ....#. ....#. #...#. #...#. .###.."),
+        ('K', "#....# #...#. #..#.. #.#... ##.... ##.... #.#... #..#.. #...#. #....#"),
+        ('L', "#..... #..... #..... #..... #..... #..... #..... #..... #..... ######"),
+        ('N', "#....# ##...# ##...# #.#..# #.#..# #..#.# #..#.# #...## #...## #....#"),
+        ('P', "#####. #....# #....# #....# #####. #..... #..... #..... #..... #....."),
+        ('R', "#####. #....# #....# #....# #####. #..#.. #...#. #...#. #....# #....#"),
+        ('X', "#....# #....# .#..#. .#..#. ..##.. ..##.. .#..#. .#..#. #....# #....#"),
+        ('Z', "###### .....# .....# ....#. ...#.. ..#... .#.... #..... #..... ######"),
+    ],
+};
+
+impl Font {
+    fn pitch(&self) -> usize {
+        self.width + self.spacing
+    }
+
+    // Reads the letters of a `#` grid whose first letter starts in column 0. Blank letter
+    // cells read as spaces.
+    fn read(&self, visual: &[Vec<char>]) -> Result<String, String> {
+        let lit = |row: usize, col: usize| {
+            visual
+                .get(row)
+                .and_then(|line| line.get(col))
+                .is_some_and(|&cell| cell == '#')
+        };
+        let cols = visual.iter().map(|line| line.len()).max().unwrap_or(0);
+
+        let mut text = String::new();
+        for index in 0..cols.div_ceil(self.pitch()) {
+            let left = index * self.pitch();
+            let rows: Vec<String> = (0..self.height)
+                .map(|row| {
+                    (left..left + self.width)
+                        .map(|col| if lit(row, col) { '#' } else { '.' })
+                        .collect()
+                })
+                .collect();
+            if let Some(col) = (left + self.width..left + self.pitch())
+                .find(|&col| (0..self.height).any(|row| lit(row, col)))
+            {
+                return Err(format!("dots between letters in column {}", col));
+            }
+
+            if rows.iter().all(|row| !row.contains('#')) {
+                text.push(' ');
+                continue;
+            }
+            match self
+                .glyphs
+                .iter()
+                .find(|(_, glyph)| glyph.split(' ').eq(rows.iter()))
+            {
+                Some(&(letter, _)) => text.push(letter),
+                None => {
+                    return Err(format!(
+                        "unknown glyph at letter {} (column {}):\n{}",
+                        index,
+                        left,
+                        rows.join("\n")
+                    ))
+                }
+            }
+        }
+        Ok(text)
+    }
+
+    // Dots spelling `message` from column 0, the inverse of `read`. A message without
+    // letters has no dots, which would not read back as itself.
+    fn render(&self, message: &str) -> Result<Vec<Point>, String> {
+        if message.trim().is_empty() {
+            return Err("message has no letters".to_string());
+        }
+        let mut points = Vec::new();
+        for (index, letter) in message.chars().enumerate() {
+            if letter == ' ' {
+                continue;
+            }
+            let (_, glyph) = self
+                .glyphs
+                .iter()
+                .find(|&&(symbol, _)| symbol == letter)
+                .ok_or_else(|| format!("no glyph for {:?} in this font", letter))?;
+            for (row, line) in glyph.split(' ').enumerate() {
+                for (col, cell) in line.chars().enumerate() {
+                    if cell == '#' {
+                        points.push(Point {
+                            row: row as u32,
+                            col: (index * self.pitch() + col) as u32,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(points)
+    }
+}
 
+// Picks the font from the height of the folded grid
+fn read_text(visual: &[Vec<char>]) -> Result<String, String> {
+    match visual.len() {
+        0 => Err("no dots to read".to_string()),
+        rows if rows <= SMALL_FONT.height => SMALL_FONT.read(visual),
+        rows if rows <= LARGE_FONT.height => LARGE_FONT.read(visual),
+        rows => Err(format!("{} rows is taller than any known font", rows)),
+    }
+}
+
+fn parse_input(contents: &str) -> (Vec<Point>, Vec<Fold>) {
     let (folds_raw, points_raw): (Vec<&str>, Vec<&str>) = contents
         .split('\n')
         .partition(|&ele| ele.contains("fold along"));
 
-    let mut points: Vec<Point> = points_raw
+    let points: Vec<Point> = points_raw
         .into_iter()
         .filter(|&ele| !ele.is_empty())
         .map(|point| {
@@ -84,6 +240,150 @@ fn main() {
         })
         .collect();
 
+    (points, folds)
+}
+
+fn draw(points: &[Point]) -> Vec<Vec<char>> {
+    let (max_rows, max_cols) = points.iter().fold((0, 0), |acc, point| {
+        (cmp::max(acc.0, point.row), cmp::max(acc.1, point.col))
+    });
+
+    let mut visual = vec![vec![' '; (max_cols + 1) as usize]; (max_rows + 1) as usize];
+
+    for point in points {
+        visual[point.row as usize][point.col as usize] = '#';
+    }
+
+    visual
+}
+
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for index in (1..items.len()).rev() {
+            items.swap(index, self.below(index + 1));
+        }
+    }
+}
+
+// Reverses `count` folds: each picks an axis and a line past every current dot, and
+// every dot is kept, mirrored across the line or both. Folding along the returned
+// instructions in order gives back the original dots.
+fn unfold(
+    mut points: Vec<Point>,
+    count: usize,
+    rng: &mut Rng,
+) -> Result<(Vec<Point>, Vec<Fold>), String> {
+    let mut rows = points.iter().map(|point| point.row + 1).max().unwrap_or(0);
+    let mut cols = points.iter().map(|point| point.col + 1).max().unwrap_or(0);
+
+    // At worst every fold lands on the same axis two past its edge, more than doubling it
+    let mut widest = rows.max(cols);
+    for _ in 0..count {
+        widest = widest
+            .checked_add(2)
+            .and_then(|location| location.checked_mul(2))
+            .and_then(|extent| extent.checked_add(1))
+            .ok_or_else(|| format!("{} folds can overflow the dot coordinates", count))?;
+    }
+
+    let mut folds = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let (direction, extent) = if rng.below(2) == 0 {
+            (Direction::Up, &mut rows)
+        } else {
+            (Direction::Left, &mut cols)
+        };
+        let location = *extent + rng.below(3) as u32;
+        *extent = 2 * location + 1;
+
+        let mut unfolded = Vec::with_capacity(points.len() * 2);
+        for point in points {
+            let mirrored = match direction {
+                Direction::Up => Point {
+                    row: 2 * location - point.row,
+                    col: point.col,
+                },
+                Direction::Left => Point {
+                    row: point.row,
+                    col: 2 * location - point.col,
+                },
+            };
+            match rng.below(3) {
+                0 => unfolded.push(point),
+                1 => unfolded.push(mirrored),
+                _ => {
+                    unfolded.push(point);
+                    unfolded.push(mirrored);
+                }
+            }
+        }
+        points = unfolded;
+        folds.push(Fold {
+            direction,
+            location,
+        });
+    }
+
+    folds.reverse();
+    rng.shuffle(&mut points);
+    Ok((points, folds))
+}
+
+// Puzzle input in the same format `parse_input` reads
+fn puzzle_text(points: &[Point], folds: &[Fold]) -> String {
+    let mut text = String::new();
+    for point in points {
+        text += &format!("{},{}\n", point.col, point.row);
+    }
+    text.push('\n');
+    for fold in folds {
+        let axis = match fold.direction {
+            Direction::Up => 'y',
+            Direction::Left => 'x',
+        };
+        text += &format!("fold along {}={}\n", axis, fold.location);
+    }
+    text
+}
+
+fn main() {
+    // `--input <file>` solves another puzzle. `--generate <message> --output <file>` writes
+    // a puzzle spelling the message, unfolded over `--folds n` random folds (default 10)
+    // seeded by `--seed`, in the 6x10 font with `--large`.
+    let args: Vec<String> = env::args().collect();
+    let flag = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .map(|index| args.get(index + 1).expect("missing flag value").as_str())
+    };
+
+    let input = flag("--input").map(|path| fs::read_to_string(path).expect("failed to read input"));
+    let contents = match &input {
+        Some(input) => input,
+        None => include_str!("day13.txt"),
+    }
+    .trim_end();
+
+    let (mut points, folds) = parse_input(contents);
+
     for (i, fold) in folds.iter().enumerate() {
         for point in points.iter_mut() {
             point.fold(fold);
@@ -95,23 +395,171 @@ fn main() {
         }
     }
 
-    let (max_rows, max_cols) = points.iter().fold((0, 0), |acc, point| {
-        (cmp::max(acc.0, point.row), cmp::max(acc.1, point.col))
-    });
+    let visual = draw(&points);
 
-    let mut visual = vec![vec![' '; (max_cols + 1) as usize]; (max_rows + 1) as usize];
+    match read_text(&visual) {
+        Ok(text) => println!("Part 2: {:?}", text),
+        Err(err) => {
+            let output: Vec<String> = visual
+                .iter()
+                .map(|row| row.iter().collect::<String>())
+                .collect();
 
-    for point in &points {
-        visual[point.row as usize][point.col as usize] = '#';
+            println!("Part 2: unreadable ({})", err);
+            for line in output {
+                println!("{}", line);
+            }
+        }
     }
 
-    let output: Vec<String> = visual
-        .iter()
-        .map(|row| row.iter().collect::<String>())
-        .collect();
+    if let Some(message) = flag("--generate") {
+        let font = if args.iter().any(|arg| arg == "--large") {
+            &LARGE_FONT
+        } else {
+            &SMALL_FONT
+        };
+        let count = flag("--folds").map_or(10, |n| n.parse().expect("invalid fold count"));
+        let seed = flag("--seed").map_or(2021, |seed| seed.parse().expect("invalid seed"));
+        let (points, folds) = font
+            .render(message)
+            .and_then(|points| unfold(points, count, &mut Rng::new(seed)))
+            .unwrap_or_else(|err| panic!("cannot generate a puzzle: {}", err));
+        let puzzle = puzzle_text(&points, &folds);
+
+        let path = flag("--output").expect("--generate needs --output <file>");
+        fs::write(path, puzzle).expect("failed to write puzzle");
+        println!(
+            "Wrote {} dots and {} folds to {}",
+            points.len(),
+            folds.len(),
+            path
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picture(rows: &[&str]) -> Vec<Vec<char>> {
+        rows.iter()
+            .map(|row| {
+                row.chars()
+                    .map(|cell| if cell == '#' { '#' } else { ' ' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn alphabet(font: &Font) -> String {
+        font.glyphs.iter().map(|&(letter, _)| letter).collect()
+    }
+
+    #[test]
+    fn reads_the_puzzle() {
+        let (mut points, folds) = parse_input(include_str!("day13.txt").trim_end());
+        for fold in &folds {
+            for point in points.iter_mut() {
+                point.fold(fold);
+            }
+        }
+        assert_eq!(read_text(&draw(&points)), Ok("RCPLAKHL".to_string()));
+    }
+
+    #[test]
+    fn reads_every_small_letter() {
+        let visual = picture(&[
+            "#..#.####.#....#.....##.",
+            "#..#.#....#....#....#..#",
+            "####.###..#....#....#..#",
+            "#..#.#....#....#....#..#",
+            "#..#.#....#....#....#..#",
+            "#..#.####.####.####..##.",
+        ]);
+        assert_eq!(read_text(&visual), Ok("HELLO".to_string()));
+
+        let message = alphabet(&SMALL_FONT);
+        assert_eq!(
+            read_text(&draw(&SMALL_FONT.render(&message).unwrap())),
+            Ok(message)
+        );
+    }
+
+    #[test]
+    fn reads_every_large_letter() {
+        let message = alphabet(&LARGE_FONT);
+        assert_eq!(
+            read_text(&draw(&LARGE_FONT.render(&message).unwrap())),
+            Ok(message)
+        );
 
-    println!("Part 2: ");
-    for line in output {
-        println!("{}", line);
+        let spaced = "AB  ZX";
+        assert_eq!(
+            read_text(&draw(&LARGE_FONT.render(spaced).unwrap())),
+            Ok(spaced.to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_glyphs() {
+        let visual = picture(&[
+            ".##..####",
+            "#..#.#..#",
+            "#..#.#..#",
+            "####.#..#",
+            "#..#.#..#",
+            "#..#.####",
+        ]);
+        let err = read_text(&visual).unwrap_err();
+        assert!(err.contains("unknown glyph at letter 1"), "{}", err);
+
+        let visual = picture(&[".##.#", "#..#.", "#..#.", "####.", "#..#.", "#..#."]);
+        assert!(read_text(&visual).is_err());
+        assert!(read_text(&picture(&["#"; 11])).is_err());
+    }
+
+    #[test]
+    fn generated_puzzles_fold_back() {
+        for (font, message) in [(&SMALL_FONT, "HI ZK"), (&LARGE_FONT, "XC  B")] {
+            for seed in [1, 2021, 987_654_321] {
+                for count in [0, 1, 5, 12] {
+                    let points = font.render(message).unwrap();
+                    let (points, folds) = unfold(points, count, &mut Rng::new(seed)).unwrap();
+                    assert_eq!(folds.len(), count);
+
+                    let (mut points, folds) = parse_input(&puzzle_text(&points, &folds));
+                    for fold in &folds {
+                        for point in points.iter_mut() {
+                            point.fold(fold);
+                        }
+                    }
+                    assert_eq!(
+                        read_text(&draw(&points)),
+                        Ok(message.to_string()),
+                        "seed {}, {} folds",
+                        seed,
+                        count
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_ungeneratable_puzzles() {
+        assert!(SMALL_FONT.render("").is_err());
+        assert!(SMALL_FONT.render("   ").is_err());
+        assert!(SMALL_FONT.render("hi").is_err());
+        assert!(LARGE_FONT.render("HI").is_err());
+
+        for count in [40, usize::MAX] {
+            let points = SMALL_FONT.render("A").unwrap();
+            assert!(unfold(points, count, &mut Rng::new(1)).is_err());
+        }
+        let near_limit = vec![Point {
+            row: 0,
+            col: u32::MAX / 2,
+        }];
+        assert!(unfold(near_limit, 1, &mut Rng::new(1)).is_err());
     }
 }
@@ -1,7 +1,7 @@
 fn parse_bounds(raw_bounds: &str) -> Vec<i32> {
     raw_bounds
         .split('=')
-        .last()
+        .next_back()
         .unwrap()
         .split("..")
         .map(|ele| ele.parse::<i32>().unwrap())
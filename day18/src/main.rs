@@ -18,11 +18,9 @@ impl Number {
                 match ele {
                     '[' => depth += 1,
                     ']' => depth -= 1,
-                    ',' => {
-                        if depth == 1 {
-                            split = index;
-                            break;
-                        }
+                    ',' if depth == 1 => {
+                        split = index;
+                        break;
                     }
                     _ => (),
                 }
@@ -63,7 +61,7 @@ impl Number {
                 if *value >= 10 {
                     *self = Number::Parent {
                         left: Box::new(Number::Leaf(*value / 2)),
-                        right: Box::new(Number::Leaf((*value + 1) / 2)),
+                        right: Box::new(Number::Leaf(value.div_ceil(2))),
                     };
                     true
                 } else {
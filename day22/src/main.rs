@@ -256,10 +256,7 @@ fn main() {
                 for volume in on.iter() {
                     current_volumes = current_volumes
                         .iter()
-                        .flat_map(|ele| match ele.subtract(volume) {
-                            None => vec![],
-                            Some(x) => x,
-                        })
+                        .flat_map(|ele| ele.subtract(volume).unwrap_or_default())
                         .collect();
                 }
                 on.append(&mut current_volumes);
@@ -267,10 +264,7 @@ fn main() {
             State::Off => {
                 on = on
                     .iter()
-                    .flat_map(|ele| match ele.subtract(&step.volume) {
-                        None => vec![],
-                        Some(x) => x,
-                    })
+                    .flat_map(|ele| ele.subtract(&step.volume).unwrap_or_default())
                     .collect()
             }
         }